@@ -1,3 +1,5 @@
+use std::fmt::Display;
+
 use jni::JNIEnv;
 use jni::objects::{JClass};
 use jni::sys::{jstring, jlong, jbyteArray, jint};
@@ -11,7 +13,7 @@ mod android_lib;
 #[no_mangle]
 pub unsafe extern
 fn Java_com_demont93_camera_1x_1app_RustBindings_newConverter(
-    _: JNIEnv,
+    env: JNIEnv,
     _: JClass,
     output_height: jint,
     output_width: jint,
@@ -21,7 +23,7 @@ fn Java_com_demont93_camera_1x_1app_RustBindings_newConverter(
     cpixel_width: jint,
 ) -> jlong
 {
-    Box::into_raw(Box::new(Converter::new(
+    let converter = Converter::new(
         &Dimensions {
             height: output_height as usize,
             width: output_width as usize,
@@ -31,7 +33,24 @@ fn Java_com_demont93_camera_1x_1app_RustBindings_newConverter(
             width: input_width as usize,
         },
         true,
-    ))) as i64
+    );
+    let mut converter = match converter {
+        Ok(converter) => converter,
+        Err(error) => {
+            throw(&env, &error);
+            return 0;
+        }
+    };
+    if cpixel_height > 0 && cpixel_width > 0 {
+        let ratio = cpixel_height as f64 / cpixel_width as f64;
+        if let Err(error) = converter.set_cell_aspect_ratio(ratio) {
+            throw(&env, &error);
+            return 0;
+        }
+    }
+    // Keep the text the same size on every frame.
+    converter.set_letterbox(Some(' '));
+    Box::into_raw(Box::new(converter)) as i64
 }
 
 #[no_mangle]
@@ -43,24 +62,58 @@ fn Java_com_demont93_camera_1x_1app_RustBindings_convert(
     buffer: jbyteArray,
 ) -> jstring
 {
+    // `newConverter` returns 0 after throwing, don't trust callers that
+    // ignored the exception.
+    if converter_i64 == 0 {
+        throw(&env, &"Converter handle is null.");
+        return std::ptr::null_mut();
+    }
     let converter = converter_i64 as *mut Converter;
     let dims = (*converter).image_settings();
-    let buffer = env.convert_byte_array(buffer).unwrap();
+    let buffer = match env.convert_byte_array(buffer) {
+        Ok(buffer) => buffer,
+        Err(error) => {
+            throw(&env, &error);
+            return std::ptr::null_mut();
+        }
+    };
     let buffer = Buffer2d::new(
         *dims,
         buffer,
     );
-    let result = (*converter).convert_one(&buffer);
+    let result = match (*converter).convert_one(&buffer) {
+        Ok(result) => result,
+        Err(error) => {
+            throw(&env, &error);
+            return std::ptr::null_mut();
+        }
+    };
 
-    let final_string = result.buffer
-        .chunks(result.dimensions.width)
-        .map(|chunk| {
-            chunk.iter().map(|m| m.0).collect::<String>()
+    let final_string = result
+        .rows()
+        .map(|row| {
+            row.iter().map(|m| m.0).collect::<String>()
         })
         .collect::<Vec<String>>()
         .as_slice()
         .join("\n");
-    env.new_string(final_string).unwrap().into_inner()
+    match env.new_string(final_string) {
+        Ok(string) => string.into_inner(),
+        Err(error) => {
+            throw(&env, &error);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Hands an error to Java as an `IllegalArgumentException`, unless a failed
+/// JNI call already left an exception pending. Nothing may panic across the
+/// FFI boundary.
+fn throw(env: &JNIEnv, error: &impl Display) {
+    if env.exception_check().unwrap_or(false) {
+        return;
+    }
+    let _ = env.throw_new("java/lang/IllegalArgumentException", error.to_string());
 }
 
 #[no_mangle]
@@ -70,5 +123,8 @@ fn Java_com_demont93_camera_1x_1app_RustBindings_dropConverter(
     _: JClass,
     converter_i64: jlong,
 ) {
+    if converter_i64 == 0 {
+        return;
+    }
     let converter = Box::from_raw(converter_i64 as *mut Converter);
 }
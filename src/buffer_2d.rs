@@ -1,17 +1,147 @@
-use crate::dimensions::{Dimensions};
+use crate::dimensions::{Dimensions, Rect};
 use crate::yuv::Yuv420;
 use crate::pixel::{Pixel, Brightness};
+use crate::image_view::{ImageView, ImageViewMut};
+use crate::error::{Error, Result};
 
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "UncheckedBuffer2d<T>"))]
 pub struct Buffer2d<T> {
     pub dimensions: Dimensions,
     pub buffer: Vec<T>,
 }
 
+/// A deserialized `Buffer2d` before its length is checked.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct UncheckedBuffer2d<T> {
+    dimensions: Dimensions,
+    buffer: Vec<T>,
+}
+
+#[cfg(feature = "serde")]
+impl<T> std::convert::TryFrom<UncheckedBuffer2d<T>> for Buffer2d<T> {
+    type Error = Error;
+
+    fn try_from(image: UncheckedBuffer2d<T>) -> Result<Self> {
+        Buffer2d::try_new(image.dimensions, image.buffer)
+    }
+}
+
 impl<T> Buffer2d<T> {
     pub fn new(dimensions: Dimensions, buffer: Vec<T>) -> Self {
         Buffer2d { dimensions, buffer }
     }
+
+    /// Like `new`, `BufferLength` unless `buffer` holds exactly
+    /// `dimensions.total()` elements.
+    pub fn try_new(dimensions: Dimensions, buffer: Vec<T>) -> Result<Self> {
+        let image = Buffer2d { dimensions, buffer };
+        image.check_len()?;
+        Ok(image)
+    }
+
+    /// `BufferLength` unless the buffer holds exactly `dimensions.total()`
+    /// elements.
+    pub fn check_len(&self) -> Result<()> {
+        if self.buffer.len() == self.dimensions.total() {
+            Ok(())
+        } else {
+            Err(Error::BufferLength {
+                expected: self.dimensions.total(),
+                actual: self.buffer.len(),
+            })
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<&T> {
+        self.index(x, y).and_then(|index| self.buffer.get(index))
+    }
+
+    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut T> {
+        self.index(x, y).and_then(move |index| self.buffer.get_mut(index))
+    }
+
+    /// Row `y`, panics if it's outside the buffer.
+    pub fn row(&self, y: usize) -> &[T] {
+        let width = self.dimensions.width;
+        assert!(y < self.dimensions.height, "Row is outside the buffer.");
+        &self.buffer[y * width..(y + 1) * width]
+    }
+
+    /// Every row from top to bottom. The buffer must hold exactly the
+    /// elements of its dimensions, see `check_len`; in release builds the
+    /// rows a short buffer is missing are skipped.
+    pub fn rows(&self) -> impl Iterator<Item=&[T]> {
+        debug_assert_eq!(self.buffer.len(), self.dimensions.total(), "Buffer length mismatch.");
+        let total = self.dimensions.total().min(self.buffer.len());
+        self.buffer[..total].chunks_exact(self.dimensions.width.max(1))
+    }
+
+    /// Mutable counterpart of `rows`, with the same requirement.
+    pub fn rows_mut(&mut self) -> impl Iterator<Item=&mut [T]> {
+        debug_assert_eq!(self.buffer.len(), self.dimensions.total(), "Buffer length mismatch.");
+        let total = self.dimensions.total().min(self.buffer.len());
+        self.buffer[..total].chunks_exact_mut(self.dimensions.width.max(1))
+    }
+
+    /// Elements of column `x` from top to bottom, empty if it's outside the
+    /// buffer.
+    pub fn column(&self, x: usize) -> impl Iterator<Item=&T> {
+        let width = self.dimensions.width;
+        let height = if x < width { self.dimensions.height } else { 0 };
+        self.buffer.iter().skip(x).step_by(width.max(1)).take(height)
+    }
+
+    pub fn columns(&self) -> impl Iterator<Item=impl Iterator<Item=&T>> {
+        (0..self.dimensions.width).map(move |x| self.column(x))
+    }
+
+    /// View of the part of the buffer inside `rect`, `OutOfBounds` if
+    /// `rect` isn't inside it.
+    pub fn sub_view(&self, rect: &Rect) -> Result<ImageView<'_, T>> {
+        ImageView::packed(&self.buffer, self.dimensions)?.sub_view(rect)
+    }
+
+    pub fn map<U>(&self, f: impl FnMut(&T) -> U) -> Buffer2d<U> {
+        Buffer2d::new(self.dimensions, self.buffer.iter().map(f).collect())
+    }
+
+    /// Combine the elements at the same position of two buffers of the same
+    /// dimensions.
+    pub fn zip_with<U, V>(
+        &self,
+        other: &Buffer2d<U>,
+        mut f: impl FnMut(&T, &U) -> V,
+    ) -> Buffer2d<V> {
+        assert_eq!(self.dimensions, other.dimensions, "Buffers have different dimensions.");
+        Buffer2d::new(
+            self.dimensions,
+            self.buffer.iter().zip(other.buffer.iter()).map(|(a, b)| f(a, b)).collect(),
+        )
+    }
+
+    fn index(&self, x: usize, y: usize) -> Option<usize> {
+        if x < self.dimensions.width && y < self.dimensions.height {
+            Some(y * self.dimensions.width + x)
+        } else {
+            None
+        }
+    }
+
+    /// Panics if the buffer is shorter than its dimensions.
+    pub fn view(&self) -> ImageView<'_, T> {
+        ImageView::packed(&self.buffer, self.dimensions)
+            .expect("Buffer is shorter than its dimensions.")
+    }
+
+    /// Panics if the buffer is shorter than its dimensions.
+    pub fn view_mut(&mut self) -> ImageViewMut<'_, T> {
+        let width = self.dimensions.width;
+        ImageViewMut::new(&mut self.buffer, self.dimensions, width, 1)
+            .expect("Buffer is shorter than its dimensions.")
+    }
 }
 
 #[allow(dead_code)]
@@ -41,14 +171,16 @@ impl<T: Default + Clone> Buffer2d<T> {
     }
 }
 
-impl From<Yuv420> for Buffer2d<u8> {
-    fn from(image: Yuv420) -> Self {
+impl From<Yuv420<'_>> for Buffer2d<u8> {
+    fn from(image: Yuv420<'_>) -> Self {
+        // Only the Y plane.
+        let total = image.dimensions.total().min(image.y.len());
         let mut ret_val = Buffer2d {
             dimensions: image.dimensions,
-            buffer: image.buffer,
+            buffer: image.y[..total].to_vec(),
         };
         ret_val.buffer.resize(
-            (ret_val.dimensions.width * ret_val.dimensions.height) as usize,
+            ret_val.dimensions.width * ret_val.dimensions.height,
             0,
         );
         ret_val
@@ -62,4 +194,84 @@ impl<T: Copy> IntoIterator for Buffer2d<T> {
     fn into_iter(self) -> Self::IntoIter {
         self.buffer.into_iter()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Dimensions, Rect, Buffer2d, Error};
+
+    // 1 2 3
+    // 4 5 6
+    fn buffer() -> Buffer2d<i32> {
+        Buffer2d::new(Dimensions { height: 2, width: 3 }, (1..=6).collect())
+    }
+
+    #[test]
+    fn test_try_new() {
+        let dimensions = Dimensions { height: 2, width: 3 };
+        assert!(Buffer2d::try_new(dimensions, vec![0; 6]).is_ok());
+        assert_eq!(
+            Buffer2d::try_new(dimensions, vec![0; 5]),
+            Err(Error::BufferLength { expected: 6, actual: 5 }),
+        );
+        assert!(Buffer2d::try_new(dimensions, vec![0; 7]).is_err());
+    }
+
+    #[test]
+    fn test_get() {
+        let mut buffer = buffer();
+        assert_eq!(buffer.get(2, 0), Some(&3));
+        assert_eq!(buffer.get(0, 1), Some(&4));
+        assert_eq!(buffer.get(3, 0), None);
+        assert_eq!(buffer.get(0, 2), None);
+        *buffer.get_mut(1, 1).unwrap() = 50;
+        assert_eq!(buffer.buffer, vec![1, 2, 3, 4, 50, 6]);
+    }
+
+    #[test]
+    fn test_rows_and_columns() {
+        let mut buffer = buffer();
+        assert_eq!(buffer.rows().collect::<Vec<&[i32]>>(), vec![&[1, 2, 3][..], &[4, 5, 6][..]]);
+        buffer.rows_mut().for_each(|row| row.reverse());
+        assert_eq!(buffer.buffer, vec![3, 2, 1, 6, 5, 4]);
+        assert_eq!(buffer.column(1).copied().collect::<Vec<i32>>(), vec![2, 5]);
+        assert_eq!(buffer.column(3).count(), 0);
+        let columns = buffer.columns()
+            .map(|column| column.copied().collect())
+            .collect::<Vec<Vec<i32>>>();
+        assert_eq!(columns, vec![vec![3, 6], vec![2, 5], vec![1, 4]]);
+        assert_eq!(buffer.row(1), &[6, 5, 4]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_row_outside() {
+        buffer().row(2);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic]
+    fn test_rows_of_short_buffer() {
+        let buffer = Buffer2d::new(Dimensions { height: 2, width: 3 }, vec![0; 5]);
+        buffer.rows().count();
+    }
+
+    #[test]
+    fn test_sub_view() {
+        let buffer = buffer();
+        let view = buffer.sub_view(&Rect { x: 1, y: 0, height: 2, width: 2 }).unwrap();
+        assert_eq!(view.to_buffer_2d().buffer, vec![2, 3, 5, 6]);
+        assert!(buffer.sub_view(&Rect { x: 2, y: 0, height: 1, width: 2 }).is_err());
+    }
+
+    #[test]
+    fn test_map_and_zip_with() {
+        let buffer = buffer();
+        let doubled = buffer.map(|&n| n * 2);
+        assert_eq!(doubled.dimensions, buffer.dimensions);
+        assert_eq!(doubled.buffer, vec![2, 4, 6, 8, 10, 12]);
+        let sums = buffer.zip_with(&doubled, |&a, &b| (a + b) as u8);
+        assert_eq!(sums.buffer, vec![3, 6, 9, 12, 15, 18]);
+    }
+}
@@ -0,0 +1,317 @@
+use crate::Buffer2d;
+use crate::converter::cpixel::Cpixel;
+use crate::parallel::for_each_row;
+
+/// How the binarization threshold is chosen.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Threshold {
+    /// Single threshold for the whole frame that best separates its
+    /// histogram in two classes.
+    Otsu,
+    /// Same threshold for every frame.
+    Fixed(u8),
+    /// Threshold each pixel against the mean of the square window of
+    /// `radius` around it, minus `offset`.
+    AdaptiveMean { radius: usize, offset: i16 },
+    /// Like `AdaptiveMean` with a gaussian weighted window, `radius` is
+    /// three standard deviations.
+    AdaptiveGaussian { radius: usize, offset: i16 },
+}
+
+/// Intermediate buffers of the adaptive thresholds, kept between frames.
+#[derive(Clone, Debug, Default)]
+pub struct BinarizeBuffers {
+    kernel: Vec<u32>,
+    rows: Buffer2d<(u64, u64)>,
+    means: Buffer2d<u8>,
+}
+
+/// Two-level output, every cell is either the `on` or the `off` glyph.
+///
+/// Cells brighter than the threshold are `on`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Binarize {
+    threshold: Threshold,
+    on: Cpixel,
+    off: Cpixel,
+}
+
+impl Binarize {
+    pub fn new(threshold: Threshold) -> Self {
+        Self { threshold, on: Cpixel('#'), off: Cpixel(' ') }
+    }
+
+    pub fn with_glyphs(threshold: Threshold, on: char, off: char) -> Self {
+        Self { threshold, on: Cpixel(on), off: Cpixel(off) }
+    }
+
+    #[allow(dead_code)]
+    pub fn threshold(&self) -> Threshold {
+        self.threshold
+    }
+
+    #[allow(dead_code)]
+    pub fn glyphs(&self) -> (char, char) {
+        (self.on.0, self.off.0)
+    }
+
+    pub fn apply(&self, image: &Buffer2d<u8>) -> Buffer2d<Cpixel> {
+        let mut output = Buffer2d::default();
+        self.apply_into(image, &mut BinarizeBuffers::default(), &mut output);
+        output
+    }
+
+    /// Like `apply`, reusing the allocations of `buffers` and `output`.
+    pub fn apply_into(
+        &self,
+        image: &Buffer2d<u8>,
+        buffers: &mut BinarizeBuffers,
+        output: &mut Buffer2d<Cpixel>,
+    ) {
+        let (on, off) = (self.on, self.off);
+        let choose = move |bright: bool| if bright { on } else { off };
+        match self.threshold {
+            Threshold::Otsu => {
+                let threshold = Self::otsu(&image.buffer);
+                Self::map_into(image, output, |_, _, n| choose(n > threshold));
+            }
+            Threshold::Fixed(threshold) => {
+                Self::map_into(image, output, |_, _, n| choose(n > threshold));
+            }
+            Threshold::AdaptiveMean { radius, offset } => {
+                Self::box_blur(image, radius, buffers);
+                let means = &buffers.means;
+                Self::map_into(image, output, |x, y, n| {
+                    choose(Self::above(n, means.row(y)[x], offset))
+                });
+            }
+            Threshold::AdaptiveGaussian { radius, offset } => {
+                Self::gaussian_blur(image, radius, buffers);
+                let means = &buffers.means;
+                Self::map_into(image, output, |x, y, n| {
+                    choose(Self::above(n, means.row(y)[x], offset))
+                });
+            }
+        }
+    }
+
+    /// Otsu's method, the returned value is the last one of the dark class.
+    pub fn otsu(pixels: &[u8]) -> u8 {
+        let mut histogram = [0_u64; 256];
+        pixels.iter().for_each(|&n| histogram[n as usize] += 1);
+        let total = pixels.len() as u64;
+        let sum: u64 = histogram.iter()
+            .enumerate()
+            .map(|(value, &count)| value as u64 * count)
+            .sum();
+
+        let mut best = (0_u8, 0_f64);
+        let mut dark_count = 0_u64;
+        let mut dark_sum = 0_u64;
+        for (value, &count) in histogram.iter().enumerate() {
+            dark_count += count;
+            dark_sum += value as u64 * count;
+            let bright_count = total - dark_count;
+            if dark_count == 0 || bright_count == 0 {
+                continue;
+            }
+            let dark_mean = dark_sum as f64 / dark_count as f64;
+            let bright_mean = (sum - dark_sum) as f64 / bright_count as f64;
+            let variance = dark_count as f64 * bright_count as f64
+                * (dark_mean - bright_mean).powi(2);
+            if variance > best.1 {
+                best = (value as u8, variance);
+            }
+        }
+        best.0
+    }
+
+    fn above(n: u8, mean: u8, offset: i16) -> bool {
+        n as i32 > mean as i32 - offset as i32
+    }
+
+    /// Fill `output` with `f` of the coordinates and value of every pixel.
+    fn map_into(
+        image: &Buffer2d<u8>,
+        output: &mut Buffer2d<Cpixel>,
+        f: impl Fn(usize, usize, u8) -> Cpixel + Send + Sync,
+    ) {
+        let width = image.dimensions.width;
+        output.buffer.clear();
+        output.buffer.resize(image.buffer.len(), Cpixel(' '));
+        output.dimensions = image.dimensions;
+        for_each_row(&mut output.buffer, width, |y, row| {
+            for (x, (out, &n)) in row.iter_mut().zip(image.row(y)).enumerate() {
+                *out = f(x, y, n);
+            }
+        });
+    }
+
+    /// Mean of the window around every pixel, clipped at the borders.
+    fn box_blur(image: &Buffer2d<u8>, radius: usize, buffers: &mut BinarizeBuffers) {
+        buffers.kernel.clear();
+        buffers.kernel.resize(Self::kernel_radius(image, radius) + 1, 1);
+        Self::separable_blur(image, buffers)
+    }
+
+    fn gaussian_blur(image: &Buffer2d<u8>, radius: usize, buffers: &mut BinarizeBuffers) {
+        let sigma = (radius as f64 / 3.).max(f64::EPSILON);
+        buffers.kernel.clear();
+        buffers.kernel.extend((0..=Self::kernel_radius(image, radius)).map(|distance| {
+            let x = distance as f64;
+            ((-x * x / (2. * sigma * sigma)).exp() * 256.).round().max(1.) as u32
+        }));
+        Self::separable_blur(image, buffers)
+    }
+
+    /// `radius` cut to the image, no window reaches further so the result
+    /// is the same.
+    fn kernel_radius(image: &Buffer2d<u8>, radius: usize) -> usize {
+        radius.min(image.dimensions.width.max(image.dimensions.height))
+    }
+
+    /// Convolve rows then columns with the symmetric kernel, given from its
+    /// center outwards in `buffers.kernel`, dividing by the weights that
+    /// fall inside the image. Leaves the result in `buffers.means`.
+    fn separable_blur(image: &Buffer2d<u8>, buffers: &mut BinarizeBuffers) {
+        let width = image.dimensions.width;
+        let height = image.dimensions.height;
+        let BinarizeBuffers { kernel, rows, means } = buffers;
+        let radius = kernel.len() - 1;
+        rows.buffer.clear();
+        rows.buffer.resize(width * height, (0, 0));
+        rows.dimensions = image.dimensions;
+        means.buffer.clear();
+        means.buffer.resize(width * height, 0);
+        means.dimensions = image.dimensions;
+        if width == 0 {
+            return;
+        }
+        let window = |center: usize, len: usize| {
+            center.saturating_sub(radius)..(center + radius + 1).min(len)
+        };
+
+        let kernel = &*kernel;
+        for_each_row(&mut rows.buffer, width, |y, out| {
+            let row = image.row(y);
+            for (x, out) in out.iter_mut().enumerate() {
+                *out = window(x, width).fold((0, 0), |(sum, weights), i| {
+                    let w = kernel[x.abs_diff(i)] as u64;
+                    (sum + row[i] as u64 * w, weights + w)
+                });
+            }
+        });
+
+        let rows = &*rows;
+        for_each_row(&mut means.buffer, width, |y, out| {
+            for (x, out) in out.iter_mut().enumerate() {
+                let (sum, weights) = window(y, height).fold((0, 0), |(sum, weights), row| {
+                    let w = kernel[y.abs_diff(row)] as u64;
+                    let (row_sum, row_weights) = rows.row(row)[x];
+                    (sum + row_sum * w, weights + row_weights * w)
+                });
+                *out = ((sum + weights / 2) / weights) as u8;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::converter::binarize::{Binarize, Threshold};
+    use crate::converter::cpixel::Cpixel;
+    use crate::{Dimensions, Buffer2d};
+
+    fn to_string(image: &Buffer2d<Cpixel>) -> String {
+        image.buffer.iter().map(|c| c.0).collect()
+    }
+
+    #[test]
+    fn test_otsu_bimodal() {
+        let pixels = [10, 12, 11, 10, 200, 210, 205, 10, 12, 208];
+        let threshold = Binarize::otsu(&pixels);
+        assert!((12..200).contains(&threshold));
+    }
+
+    #[test]
+    fn test_otsu_single_value() {
+        assert_eq!(Binarize::otsu(&[42; 10]), 0);
+    }
+
+    #[test]
+    fn test_fixed() {
+        let image = Buffer2d::new(
+            Dimensions { height: 1, width: 4 },
+            vec![0, 127, 128, 255],
+        );
+        let result = Binarize::with_glyphs(Threshold::Fixed(127), '@', '.')
+            .apply(&image);
+        assert_eq!(result.dimensions, image.dimensions);
+        assert_eq!(to_string(&result), "..@@");
+    }
+
+    #[test]
+    fn test_otsu_output() {
+        let image = Buffer2d::new(
+            Dimensions { height: 2, width: 3 },
+            vec![20, 220, 20, 220, 20, 230],
+        );
+        let result = Binarize::new(Threshold::Otsu).apply(&image);
+        assert_eq!(to_string(&result), " # # #");
+    }
+
+    #[test]
+    fn test_adaptive_mean_follows_gradient() {
+        // Dark strokes on a background that gets brighter to the right, no
+        // global threshold splits them.
+        let image = Buffer2d::new(
+            Dimensions { height: 1, width: 8 },
+            vec![60, 30, 100, 110, 70, 150, 160, 120],
+        );
+        let result = Binarize::new(Threshold::AdaptiveMean { radius: 2, offset: 0 })
+            .apply(&image);
+        assert_eq!(to_string(&result), "  ## ## ");
+    }
+
+    #[test]
+    fn test_adaptive_gaussian_uniform() {
+        let image = Buffer2d::new(Dimensions { height: 3, width: 3 }, vec![90; 9]);
+        let bright = Binarize::new(Threshold::AdaptiveGaussian { radius: 1, offset: 5 })
+            .apply(&image);
+        assert_eq!(to_string(&bright), "#########");
+        let dark = Binarize::new(Threshold::AdaptiveGaussian { radius: 1, offset: -5 })
+            .apply(&image);
+        assert_eq!(to_string(&dark), "         ");
+    }
+
+    #[test]
+    fn test_extreme_offsets() {
+        let image = Buffer2d::new(Dimensions { height: 2, width: 2 }, vec![0, 255, 255, 0]);
+        let never = Binarize::new(Threshold::AdaptiveMean { radius: 1, offset: i16::MIN })
+            .apply(&image);
+        assert_eq!(to_string(&never), "    ");
+        let always = Binarize::new(Threshold::AdaptiveMean { radius: 1, offset: i16::MAX })
+            .apply(&image);
+        assert_eq!(to_string(&always), "####");
+    }
+
+    #[test]
+    fn test_huge_radius() {
+        let image = Buffer2d::new(
+            Dimensions { height: 2, width: 3 },
+            vec![10, 200, 30, 220, 40, 250],
+        );
+        // Every window already covers the image at a radius of 3, and such a
+        // wide gaussian weights it evenly.
+        let expected = Binarize::new(Threshold::AdaptiveMean { radius: 3, offset: 0 })
+            .apply(&image);
+        for threshold in [
+            Threshold::AdaptiveMean { radius: usize::MAX, offset: 0 },
+            Threshold::AdaptiveGaussian { radius: usize::MAX, offset: 0 },
+        ] {
+            assert_eq!(Binarize::new(threshold).apply(&image), expected);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use crate::{FitMode, Orientation};
+use crate::converter::{OutputMode, Filter, Reduction, TemporalDenoise};
+
+/// Every setting of a [`Converter`](crate::Converter) that doesn't depend on
+/// the frames or the screen, so it can be saved as a preset and applied to
+/// converters of any dimensions.
+///
+/// With the `serde` feature, missing fields take their default value.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ConverterConfig {
+    /// Glyphs the brightness is mapped to.
+    pub output_mode: OutputMode,
+    pub fit_mode: FitMode,
+    pub maximize_contrast: bool,
+    pub filter: Filter,
+    pub reduction: Reduction,
+    /// Height over width of an output character cell.
+    pub cell_aspect_ratio: f64,
+    pub orientation: Orientation,
+    /// Pad glyph, see [`Converter::set_letterbox`](crate::Converter::set_letterbox).
+    pub letterbox: Option<char>,
+    /// Settings of the temporal filter, without its history.
+    pub temporal_denoise: Option<TemporalDenoise>,
+}
+
+impl Default for ConverterConfig {
+    fn default() -> Self {
+        Self {
+            output_mode: OutputMode::Grayscale,
+            fit_mode: FitMode::default(),
+            maximize_contrast: false,
+            filter: Filter::default(),
+            reduction: Reduction::default(),
+            cell_aspect_ratio: 1.,
+            orientation: Orientation::default(),
+            letterbox: None,
+            temporal_denoise: None,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use crate::{
+        ConverterConfig, OutputMode, Binarize, Threshold, FitMode, Filter, Rotation,
+        TemporalDenoise,
+    };
+    use crate::{Dimensions, Buffer2d, Cpixel};
+
+    #[test]
+    fn test_config_round_trip() {
+        let mut config = ConverterConfig {
+            output_mode: OutputMode::Binary(
+                Binarize::with_glyphs(Threshold::AdaptiveMean { radius: 3, offset: -2 }, '@', '.')
+            ),
+            fit_mode: FitMode::Cover,
+            maximize_contrast: true,
+            filter: Filter::Lanczos3,
+            cell_aspect_ratio: 2.,
+            letterbox: Some(' '),
+            temporal_denoise: Some(TemporalDenoise::new(0.75, 32)),
+            ..ConverterConfig::default()
+        };
+        config.orientation.rotation = Rotation::Quarter;
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(serde_json::from_str::<ConverterConfig>(&json).unwrap(), config);
+        assert!(json.contains(r#""temporal_denoise":{"strength":0.75,"motion_threshold":32}"#));
+    }
+
+    #[test]
+    fn test_config_missing_fields() {
+        let config: ConverterConfig = serde_json::from_str(r#"{"fit_mode":"Stretch"}"#).unwrap();
+        assert_eq!(config, ConverterConfig { fit_mode: FitMode::Stretch, ..Default::default() });
+    }
+
+    #[test]
+    fn test_buffer_round_trip() {
+        let buffer = Buffer2d::new(
+            Dimensions { height: 1, width: 3 },
+            vec![Cpixel('a'), Cpixel(' '), Cpixel('#')],
+        );
+        let json = serde_json::to_string(&buffer).unwrap();
+        assert_eq!(json, r##"{"dimensions":{"height":1,"width":3},"buffer":["a"," ","#"]}"##);
+        assert_eq!(serde_json::from_str::<Buffer2d<Cpixel>>(&json).unwrap(), buffer);
+    }
+
+    #[test]
+    fn test_buffer_length_is_checked() {
+        let buffer = Buffer2d::new(Dimensions { height: 2, width: 3 }, vec![Cpixel('a'); 5]);
+        let json = serde_json::to_string(&buffer).unwrap();
+        let error = serde_json::from_str::<Buffer2d<Cpixel>>(&json).unwrap_err();
+        assert_eq!(error.to_string(), "Expected a buffer of 6 elements, got 5.");
+    }
+}
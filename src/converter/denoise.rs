@@ -0,0 +1,187 @@
+use crate::{Dimensions, Buffer2d};
+use crate::parallel::for_each_zip;
+
+/// Recursive temporal filter for consecutive luma frames.
+///
+/// Every frame is blended with the previous filtered frame. Static pixels
+/// take up to `strength` of their value from the history, pixels whose
+/// luma changed by `motion_threshold` or more take none of it, so moving
+/// regions don't leave a trail behind.
+///
+/// With the `serde` feature only the settings are saved, the history starts
+/// empty. Two filters with the same settings are equal.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(into = "DenoiseSettings", from = "DenoiseSettings"))]
+pub struct TemporalDenoise {
+    history_weight: u32,
+    motion_threshold: u32,
+    previous: Buffer2d<u16>,
+}
+
+/// Serialized form of a `TemporalDenoise`, read back through `new` so the
+/// settings are clamped like any other.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct DenoiseSettings {
+    strength: f64,
+    motion_threshold: u8,
+}
+
+#[cfg(feature = "serde")]
+impl From<DenoiseSettings> for TemporalDenoise {
+    fn from(settings: DenoiseSettings) -> Self {
+        TemporalDenoise::new(settings.strength, settings.motion_threshold)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<TemporalDenoise> for DenoiseSettings {
+    fn from(denoise: TemporalDenoise) -> Self {
+        DenoiseSettings {
+            strength: denoise.strength(),
+            motion_threshold: denoise.motion_threshold(),
+        }
+    }
+}
+
+impl PartialEq for TemporalDenoise {
+    fn eq(&self, other: &Self) -> bool {
+        self.history_weight == other.history_weight
+            && self.motion_threshold == other.motion_threshold
+    }
+}
+
+impl TemporalDenoise {
+    /// Fixed point precision of the stored history, in bits.
+    const PRECISION: u32 = 8;
+    const ONE: u32 = 1 << Self::PRECISION;
+
+    pub fn new(strength: f64, motion_threshold: u8) -> Self {
+        let strength = if strength.is_nan() { 0. } else { strength.clamp(0., 1.) };
+        Self {
+            // Never give the history a full weight, or static frames would
+            // freeze forever.
+            history_weight: ((strength * Self::ONE as f64) as u32).min(Self::ONE - 1),
+            motion_threshold: motion_threshold as u32,
+            previous: Buffer2d::default(),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn strength(&self) -> f64 {
+        self.history_weight as f64 / Self::ONE as f64
+    }
+
+    #[allow(dead_code)]
+    pub fn motion_threshold(&self) -> u8 {
+        self.motion_threshold as u8
+    }
+
+    /// The same settings with an empty history.
+    pub fn settings(&self) -> Self {
+        Self {
+            history_weight: self.history_weight,
+            motion_threshold: self.motion_threshold,
+            previous: Buffer2d::default(),
+        }
+    }
+
+    /// Forget the previous frame, the next frame passes through unfiltered.
+    pub fn reset(&mut self) {
+        self.previous = Buffer2d::default();
+    }
+
+    /// Filter `frame` in place and keep it as history for the next call.
+    pub fn apply(&mut self, frame: &mut Buffer2d<u8>) {
+        if self.previous.dimensions != frame.dimensions
+            || self.previous.buffer.len() != frame.buffer.len() {
+            self.previous = Buffer2d::new(
+                frame.dimensions,
+                frame.buffer.iter().map(|&n| (n as u16) << Self::PRECISION).collect(),
+            );
+            return;
+        }
+        let (history_weight, motion_threshold) = (self.history_weight, self.motion_threshold);
+        let width = frame.dimensions.width;
+        for_each_zip(&mut frame.buffer, &mut self.previous.buffer, width, |current, previous| {
+            let weight = Self::weight(history_weight, motion_threshold, *current, *previous);
+            let blended = (*previous as u32 * weight
+                + ((*current as u32) << Self::PRECISION) * (Self::ONE - weight))
+                >> Self::PRECISION;
+            *previous = blended as u16;
+            *current = ((blended + Self::ONE / 2) >> Self::PRECISION).min(u8::MAX as u32) as u8;
+        });
+    }
+
+    #[allow(dead_code)]
+    pub fn dimensions(&self) -> Dimensions {
+        self.previous.dimensions
+    }
+
+    fn weight(history_weight: u32, motion_threshold: u32, current: u8, previous: u16) -> u32 {
+        let previous = (previous as u32 + Self::ONE / 2) >> Self::PRECISION;
+        let difference = (current as u32).abs_diff(previous);
+        if difference >= motion_threshold {
+            0
+        } else {
+            history_weight * (motion_threshold - difference) / motion_threshold
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::converter::denoise::TemporalDenoise;
+    use crate::{Dimensions, Buffer2d};
+
+    fn frame(buffer: Vec<u8>) -> Buffer2d<u8> {
+        Buffer2d::new(Dimensions { height: 1, width: buffer.len() }, buffer)
+    }
+
+    #[test]
+    fn test_first_frame_passes_through() {
+        let mut denoise = TemporalDenoise::new(0.75, 32);
+        let mut result = frame(vec![10, 200, 35]);
+        denoise.apply(&mut result);
+        assert_eq!(result.buffer, vec![10, 200, 35]);
+    }
+
+    #[test]
+    fn test_static_noise_is_smoothed() {
+        let mut denoise = TemporalDenoise::new(0.75, 32);
+        denoise.apply(&mut frame(vec![100; 4]));
+        let mut result = frame(vec![108, 92, 100, 104]);
+        denoise.apply(&mut result);
+        assert_eq!(result.buffer, vec![104, 97, 100, 101]);
+    }
+
+    #[test]
+    fn test_motion_is_not_blended() {
+        let mut denoise = TemporalDenoise::new(0.75, 32);
+        denoise.apply(&mut frame(vec![0, 255]));
+        let mut result = frame(vec![200, 10]);
+        denoise.apply(&mut result);
+        assert_eq!(result.buffer, vec![200, 10]);
+    }
+
+    #[test]
+    fn test_dimensions_change_resets_history() {
+        let mut denoise = TemporalDenoise::new(0.75, 32);
+        denoise.apply(&mut frame(vec![100; 4]));
+        let mut result = frame(vec![108, 92]);
+        denoise.apply(&mut result);
+        assert_eq!(result.buffer, vec![108, 92]);
+        assert_eq!(denoise.dimensions(), Dimensions { height: 1, width: 2 });
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut denoise = TemporalDenoise::new(0.75, 32);
+        denoise.apply(&mut frame(vec![100; 2]));
+        denoise.reset();
+        let mut result = frame(vec![108, 92]);
+        denoise.apply(&mut result);
+        assert_eq!(result.buffer, vec![108, 92]);
+    }
+}
@@ -1,56 +1,340 @@
-use crate::Dimensions;
+use std::convert::TryInto;
+use crate::{Dimensions, FitMode, Rect, Orientation, Error, Result};
 use crate::buffer_2d::Buffer2d;
-use scale::Scale;
+use crate::image_view::ImageView;
+use crate::yuv::{Yuv420, YuvSemiPlanar, Yuv420Planes, Yuv422, ColorRange};
+use crate::parallel::for_each_row;
+pub use scale::{Scale, Filter, Reduction, Sample, Channel};
 use itertools::{MinMaxResult, Itertools};
-use cpixel::Cpixel;
+pub use cpixel::Cpixel;
+pub use denoise::TemporalDenoise;
+pub use binarize::{Binarize, Threshold};
+pub use config::ConverterConfig;
+use binarize::BinarizeBuffers;
 
 mod scale;
 mod cpixel;
+mod denoise;
+mod binarize;
+mod config;
+mod simd;
+
+/// How scaled brightness values become glyphs.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OutputMode {
+    /// One glyph per brightness level.
+    Grayscale,
+    /// Only two glyphs, see [`Binarize`].
+    Binary(Binarize),
+}
 
 pub struct Converter {
     scale: Scale,
     output_constraints: Dimensions,
     input_image_dimensions: Dimensions,
     output_dimensions: Dimensions,
+    fit_mode: FitMode,
+    /// Height over width of an output character cell.
+    cell_aspect_ratio: f64,
+    /// Width over height of an input pixel.
+    sample_aspect_ratio: f64,
+    orientation: Orientation,
+    crop: Option<Rect>,
+    /// Part of the oriented input that's scaled, the crop further cut by
+    /// `FitMode::Cover`. `None` when it's the whole input.
+    source: Option<Rect>,
     maximize_contrast: bool,
+    temporal_denoise: Option<TemporalDenoise>,
+    output_mode: OutputMode,
+    input_buffer: Buffer2d<u8>,
+    scaled_buffer: Buffer2d<u8>,
+    binarize_buffers: BinarizeBuffers,
+    /// Pad glyph around the image, which is then centered in an output of
+    /// exactly `output_constraints`.
+    letterbox: Option<Cpixel>,
+    /// The image before it's letterboxed.
+    glyph_buffer: Buffer2d<Cpixel>,
 }
 
 impl Converter {
+    /// `DimensionsMismatch` if `image` isn't of the input dimensions,
+    /// `BufferLength` if its buffer doesn't match its dimensions.
     #[allow(dead_code)]
     pub fn convert_one<T: Into<u8> + Clone>(
         &mut self,
         image: &Buffer2d<T>,
-    ) -> Buffer2d<Cpixel> {
-        let buffer = self.scale.resize(image);
-        Buffer2d {
-            buffer: buffer.buffer
-                .iter()
-                .map(|&n| Cpixel::from_brightness(n.into()))
-                .collect(),
-            dimensions: buffer.dimensions,
+    ) -> Result<Buffer2d<Cpixel>> {
+        image.check_len()?;
+        let mut input_buffer = std::mem::take(&mut self.input_buffer);
+        input_buffer.buffer.clear();
+        input_buffer.buffer.extend(image.buffer.iter().map(|n| n.clone().into()));
+        input_buffer.dimensions = image.dimensions;
+
+        let mut output = Buffer2d::default();
+        let result = self.convert_into(&input_buffer, &mut output);
+        self.input_buffer = input_buffer;
+        result.map(|_| output)
+    }
+
+    /// Convert `image` into `output`, reusing its allocation. `image` can be
+    /// any strided view, it's read in place.
+    ///
+    /// Every intermediate buffer is kept by the converter, so once `output`
+    /// and the converter have seen a frame of the current dimensions no
+    /// further allocation happens. `BufferLength` if `image` is a `Buffer2d`
+    /// shorter than its dimensions.
+    #[allow(dead_code)]
+    pub fn convert_into<'a, I>(
+        &mut self,
+        image: I,
+        output: &mut Buffer2d<Cpixel>,
+    ) -> Result<()> where I: TryInto<ImageView<'a, u8>>, Error: From<I::Error> {
+        self.convert_range_into(image.try_into()?, ColorRange::Full, output)
+    }
+
+    /// Like `convert_into`, for luma of `range`. Limited range is expanded
+    /// once scaled, before anything else looks at the brightness, and the
+    /// contrast maximized once denoised.
+    fn convert_range_into(
+        &mut self,
+        image: ImageView<'_, u8>,
+        range: ColorRange,
+        output: &mut Buffer2d<Cpixel>,
+    ) -> Result<()> {
+        if image.dimensions() != self.input_image_dimensions {
+            return Err(Error::DimensionsMismatch {
+                expected: self.input_image_dimensions,
+                actual: image.dimensions(),
+            });
+        }
+        let image = image.oriented(&self.orientation);
+        let image = match &self.source {
+            Some(source) => image.sub_view(source)?,
+            None => image,
+        };
+        self.scale.resize_into(image, &mut self.scaled_buffer)?;
+        range.expand_luma(&mut self.scaled_buffer.buffer);
+        if let Some(denoise) = &mut self.temporal_denoise {
+            denoise.apply(&mut self.scaled_buffer);
+        }
+        // After denoising, so noise doesn't move the extremes from frame to
+        // frame and make the whole image flicker.
+        Self::maybe_maximize_contrast(self.maximize_contrast, &mut self.scaled_buffer.buffer);
+        let glyphs = if self.letterbox.is_some() { &mut self.glyph_buffer } else { &mut *output };
+        match &self.output_mode {
+            OutputMode::Grayscale => {
+                let scaled = &self.scaled_buffer;
+                let width = scaled.dimensions.width;
+                glyphs.buffer.clear();
+                glyphs.buffer.resize(scaled.buffer.len(), Cpixel(' '));
+                glyphs.dimensions = scaled.dimensions;
+                for_each_row(&mut glyphs.buffer, width, |y, row| {
+                    simd::map_glyphs(scaled.row(y), row);
+                });
+            }
+            OutputMode::Binary(binarize) => {
+                binarize.apply_into(&self.scaled_buffer, &mut self.binarize_buffers, glyphs)
+            }
+        }
+        if let Some(pad) = self.letterbox {
+            Self::pad_to_frame(&self.glyph_buffer, &self.output_constraints, pad, output);
+        }
+        Ok(())
+    }
+
+    /// Center `image` in an `output` of `frame`, filling the margins with
+    /// `pad` and cutting what doesn't fit.
+    fn pad_to_frame(
+        image: &Buffer2d<Cpixel>,
+        frame: &Dimensions,
+        pad: Cpixel,
+        output: &mut Buffer2d<Cpixel>,
+    ) {
+        let margin = |image: usize, frame: usize| {
+            (frame.saturating_sub(image) / 2, image.saturating_sub(frame) / 2)
+        };
+        let (left, skip_x) = margin(image.dimensions.width, frame.width);
+        let (top, skip_y) = margin(image.dimensions.height, frame.height);
+        let width = image.dimensions.width.min(frame.width);
+
+        output.buffer.clear();
+        output.buffer.resize(frame.total(), pad);
+        output.dimensions = *frame;
+        let source_rows = image.rows().skip(skip_y);
+        for (row, source) in output.rows_mut().skip(top).zip(source_rows) {
+            row[left..left + width].copy_from_slice(&source[skip_x..skip_x + width]);
         }
     }
+
+    /// Convert the luma of `image`, following its color range. Its sample
+    /// aspect ratio replaces the one of the converter when set, frames
+    /// without one keep it. `BufferLength` if a plane is too short, the
+    /// chroma is never read.
+    #[allow(dead_code)]
+    pub fn convert_yuv420_into(
+        &mut self,
+        image: &Yuv420<'_>,
+        output: &mut Buffer2d<Cpixel>,
+    ) -> Result<()> {
+        image.check_len()?;
+        self.convert_luma_into(image.luma()?, image.range, image.sample_aspect_ratio, output)
+    }
+
+    /// Like `convert_yuv420_into`, for NV12 and NV21 frames.
+    #[allow(dead_code)]
+    pub fn convert_semi_planar_into(
+        &mut self,
+        image: &YuvSemiPlanar<'_>,
+        output: &mut Buffer2d<Cpixel>,
+    ) -> Result<()> {
+        image.check_len()?;
+        self.convert_luma_into(image.luma()?, image.range, image.sample_aspect_ratio, output)
+    }
+
+    /// Like `convert_yuv420_into`, for frames made of planes of any layout
+    /// such as Camera2 `YUV_420_888` images. `InvalidLayout` if a plane
+    /// doesn't fit in its buffer.
+    #[allow(dead_code)]
+    pub fn convert_planes_into(
+        &mut self,
+        image: &Yuv420Planes<'_>,
+        output: &mut Buffer2d<Cpixel>,
+    ) -> Result<()> {
+        image.check_layout()?;
+        self.convert_luma_into(image.luma()?, image.range, image.sample_aspect_ratio, output)
+    }
+
+    /// Like `convert_yuv420_into`, for packed YUYV and UYVY frames, read in
+    /// place. `InvalidLayout` if the rows don't fit in the buffer.
+    #[allow(dead_code)]
+    pub fn convert_yuv422_into(
+        &mut self,
+        image: &Yuv422<'_>,
+        output: &mut Buffer2d<Cpixel>,
+    ) -> Result<()> {
+        image.check_layout()?;
+        self.convert_luma_into(image.luma()?, image.range, image.sample_aspect_ratio, output)
+    }
+
+    fn convert_luma_into(
+        &mut self,
+        luma: ImageView<'_, u8>,
+        range: ColorRange,
+        sample_aspect_ratio: Option<f64>,
+        output: &mut Buffer2d<Cpixel>,
+    ) -> Result<()> {
+        if let Some(ratio) = sample_aspect_ratio {
+            if ratio != self.sample_aspect_ratio {
+                self.set_sample_aspect_ratio(ratio)?;
+            }
+        }
+        self.convert_range_into(luma, range, output)
+    }
 }
 
 impl Converter {
+    /// `EmptyDimensions` if the constraints or the input have no width or
+    /// no height.
     #[allow(dead_code)]
     pub fn new(
         output_constraints: &Dimensions,
         input_image_dimensions: &Dimensions,
         maximize_contrast: bool,
-    ) -> Self {
+    ) -> Result<Self> {
+        Self::with_filter(
+            output_constraints,
+            input_image_dimensions,
+            maximize_contrast,
+            Filter::default(),
+        )
+    }
+
+    /// Like `new`, resampling with `filter`.
+    #[allow(dead_code)]
+    pub fn with_filter(
+        output_constraints: &Dimensions,
+        input_image_dimensions: &Dimensions,
+        maximize_contrast: bool,
+        filter: Filter,
+    ) -> Result<Self> {
         let output_dimensions = Self::generate_output_dimensions(
             input_image_dimensions,
             output_constraints,
-        );
-        Self {
-            scale: Scale::new(input_image_dimensions, &output_dimensions),
+            FitMode::default(),
+            1.,
+        )?;
+        Ok(Self {
+            scale: Scale::with_filter(input_image_dimensions, &output_dimensions, filter),
             output_constraints: *output_constraints,
             input_image_dimensions: *input_image_dimensions,
             output_dimensions,
+            fit_mode: FitMode::default(),
+            cell_aspect_ratio: 1.,
+            sample_aspect_ratio: 1.,
+            orientation: Orientation::default(),
+            crop: None,
+            source: None,
             maximize_contrast,
+            temporal_denoise: None,
+            output_mode: OutputMode::Grayscale,
+            input_buffer: Buffer2d::default(),
+            scaled_buffer: Buffer2d::default(),
+            binarize_buffers: BinarizeBuffers::default(),
+            letterbox: None,
+            glyph_buffer: Buffer2d::default(),
+        })
+    }
+
+    /// Like `new`, with every setting taken from `config`. `InvalidRatio`
+    /// if its cell aspect ratio isn't positive.
+    #[allow(dead_code)]
+    pub fn with_config(
+        output_constraints: &Dimensions,
+        input_image_dimensions: &Dimensions,
+        config: &ConverterConfig,
+    ) -> Result<Self> {
+        let mut converter = Self::new(output_constraints, input_image_dimensions, false)?;
+        converter.apply_config(config)?;
+        Ok(converter)
+    }
+}
+
+impl Converter {
+    /// The settings of the converter, to save as a preset.
+    #[allow(dead_code)]
+    pub fn config(&self) -> ConverterConfig {
+        ConverterConfig {
+            output_mode: self.output_mode,
+            fit_mode: self.fit_mode,
+            maximize_contrast: self.maximize_contrast,
+            filter: self.scale.get_filter(),
+            reduction: self.scale.get_reduction(),
+            cell_aspect_ratio: self.cell_aspect_ratio,
+            orientation: self.orientation,
+            letterbox: self.letterbox(),
+            temporal_denoise: self.temporal_denoise.as_ref().map(TemporalDenoise::settings),
         }
     }
+
+    /// Replace every setting with those of `config`. Nothing changes if it
+    /// returns `InvalidRatio`. The crop is cleared when the orientation
+    /// changes.
+    #[allow(dead_code)]
+    pub fn apply_config(&mut self, config: &ConverterConfig) -> Result<()> {
+        self.cell_aspect_ratio = Self::check_ratio(config.cell_aspect_ratio)?;
+        if config.orientation != self.orientation {
+            self.orientation = config.orientation;
+            self.crop = None;
+        }
+        self.output_mode = config.output_mode;
+        self.fit_mode = config.fit_mode;
+        self.maximize_contrast = config.maximize_contrast;
+        self.set_letterbox(config.letterbox);
+        self.temporal_denoise = config.temporal_denoise.as_ref().map(TemporalDenoise::settings);
+        self.update_geometry(config.filter, config.reduction);
+        Ok(())
+    }
 }
 
 impl Converter {
@@ -59,6 +343,152 @@ impl Converter {
         self.maximize_contrast
     }
 
+    /// Blend every converted frame with the previous ones, `None` disables
+    /// the filter. Frames are filtered after scaling, at output resolution.
+    #[allow(dead_code)]
+    pub fn set_temporal_denoise(&mut self, denoise: Option<TemporalDenoise>) {
+        self.temporal_denoise = denoise;
+    }
+
+    #[allow(dead_code)]
+    pub fn temporal_denoise(&self) -> Option<&TemporalDenoise> {
+        self.temporal_denoise.as_ref()
+    }
+
+    /// Drop the frame history, e.g. after a scene cut or camera switch.
+    #[allow(dead_code)]
+    pub fn reset_temporal_denoise(&mut self) {
+        if let Some(denoise) = &mut self.temporal_denoise {
+            denoise.reset();
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn set_output_mode(&mut self, output_mode: OutputMode) {
+        self.output_mode = output_mode;
+    }
+
+    #[allow(dead_code)]
+    pub fn output_mode(&self) -> &OutputMode {
+        &self.output_mode
+    }
+
+    #[allow(dead_code)]
+    pub fn filter(&self) -> Filter {
+        self.scale.get_filter()
+    }
+
+    /// Combine the pixels under every cell with `reduction` when the image
+    /// is downscaled.
+    #[allow(dead_code)]
+    pub fn set_reduction(&mut self, reduction: Reduction) {
+        self.update_geometry(self.scale.get_filter(), reduction);
+    }
+
+    #[allow(dead_code)]
+    pub fn reduction(&self) -> Reduction {
+        self.scale.get_reduction()
+    }
+
+    /// How the converted part of the input is sized to the constraints.
+    #[allow(dead_code)]
+    pub fn set_fit_mode(&mut self, fit_mode: FitMode) {
+        self.fit_mode = fit_mode;
+        self.update_geometry(self.scale.get_filter(), self.scale.get_reduction());
+    }
+
+    #[allow(dead_code)]
+    pub fn fit_mode(&self) -> FitMode {
+        self.fit_mode
+    }
+
+    /// Height over width of the character cells the output is shown in,
+    /// so the image keeps its proportions on screen. Terminal and monospace
+    /// fonts are usually around 2.
+    #[allow(dead_code)]
+    pub fn set_cell_aspect_ratio(&mut self, cell_aspect_ratio: f64) -> Result<()> {
+        self.cell_aspect_ratio = Self::check_ratio(cell_aspect_ratio)?;
+        self.update_geometry(self.scale.get_filter(), self.scale.get_reduction());
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    pub fn cell_aspect_ratio(&self) -> f64 {
+        self.cell_aspect_ratio
+    }
+
+    /// Width over height of the input pixels, for anamorphic frames. Given
+    /// for the input as it arrives, before it's rotated.
+    #[allow(dead_code)]
+    pub fn set_sample_aspect_ratio(&mut self, sample_aspect_ratio: f64) -> Result<()> {
+        self.sample_aspect_ratio = Self::check_ratio(sample_aspect_ratio)?;
+        self.update_geometry(self.scale.get_filter(), self.scale.get_reduction());
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    pub fn sample_aspect_ratio(&self) -> f64 {
+        self.sample_aspect_ratio
+    }
+
+    /// Always output exactly the constraints, with the image centered and
+    /// the margins filled with `pad`. `None` outputs only the image.
+    #[allow(dead_code)]
+    pub fn set_letterbox(&mut self, pad: Option<char>) {
+        self.letterbox = pad.map(Cpixel);
+    }
+
+    #[allow(dead_code)]
+    pub fn letterbox(&self) -> Option<char> {
+        self.letterbox.map(|pad| pad.0)
+    }
+
+    /// Rotate and mirror every frame while it's scaled. The output
+    /// dimensions follow the oriented frame and the crop is cleared, since
+    /// it's given in oriented coordinates.
+    #[allow(dead_code)]
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+        self.crop = None;
+        self.update_geometry(self.scale.get_filter(), self.scale.get_reduction());
+    }
+
+    #[allow(dead_code)]
+    pub fn orientation(&self) -> &Orientation {
+        &self.orientation
+    }
+
+    /// Convert only the part of the oriented input inside `crop`, `None`
+    /// converts the whole input. The crop is moved or shrunk to fit inside
+    /// the input and the output dimensions follow its aspect ratio.
+    #[allow(dead_code)]
+    pub fn set_crop(&mut self, crop: Option<Rect>) {
+        let frame = self.oriented_input_dimensions();
+        self.crop = crop.map(|crop| crop.fit_inside(&frame));
+        self.update_geometry(self.scale.get_filter(), self.scale.get_reduction());
+    }
+
+    #[allow(dead_code)]
+    pub fn crop(&self) -> Option<&Rect> {
+        self.crop.as_ref()
+    }
+
+    /// Zoom in by `factor` around the center of the current crop, or out
+    /// for a factor below 1, never past the whole input.
+    #[allow(dead_code)]
+    pub fn zoom(&mut self, factor: f64) {
+        let crop = self.source_rect().zoomed(factor, &self.oriented_input_dimensions());
+        self.set_crop(Some(crop));
+    }
+
+    /// Move the crop by `dx` and `dy` input pixels, stopping at the borders
+    /// of the input.
+    #[allow(dead_code)]
+    pub fn pan(&mut self, dx: isize, dy: isize) {
+        let crop = self.source_rect().panned(dx, dy, &self.oriented_input_dimensions());
+        self.set_crop(Some(crop));
+    }
+
     #[allow(dead_code)]
     pub fn constraints(&self) -> &Dimensions {
         &self.output_constraints
@@ -74,15 +504,80 @@ impl Converter {
         &self.output_dimensions
     }
 
+    fn check_ratio(ratio: f64) -> Result<f64> {
+        if ratio.is_finite() && ratio > 0. {
+            Ok(ratio)
+        } else {
+            Err(Error::InvalidRatio(ratio))
+        }
+    }
+
+    /// Dimensions of the input once rotated.
+    fn oriented_input_dimensions(&self) -> Dimensions {
+        self.orientation.apply(&self.input_image_dimensions)
+    }
+
+    /// How much wider than its pixel count the oriented input looks, in
+    /// cells.
+    fn stretch(&self) -> f64 {
+        let sample_aspect_ratio = if self.orientation.swaps_axes() {
+            1. / self.sample_aspect_ratio
+        } else {
+            self.sample_aspect_ratio
+        };
+        sample_aspect_ratio * self.cell_aspect_ratio
+    }
+
+    /// Part of the oriented input that's converted.
+    fn source_rect(&self) -> Rect {
+        self.crop.unwrap_or_else(|| Rect::full(&self.oriented_input_dimensions()))
+    }
+
+    /// Recompute the output dimensions and the scale after the source
+    /// changed.
+    fn update_geometry(&mut self, filter: Filter, reduction: Reduction) {
+        let mut source = self.source_rect();
+        self.output_dimensions = Self::generate_output_dimensions(
+            &source.dimensions(),
+            &self.output_constraints,
+            self.fit_mode,
+            self.stretch(),
+        ).expect("Dimensions were checked when the converter was built.");
+        if self.fit_mode == FitMode::Cover {
+            let ratio = self.output_dimensions.ratio() * self.stretch();
+            source = source.centered_with_ratio(ratio);
+        }
+        self.source = if source == Rect::full(&self.oriented_input_dimensions()) {
+            None
+        } else {
+            Some(source)
+        };
+        self.scale = Scale::with_reduction(
+            &source.dimensions(),
+            &self.output_dimensions,
+            filter,
+            reduction,
+        );
+        self.reset_temporal_denoise();
+    }
+
     fn generate_output_dimensions(
         image_dimensions: &Dimensions,
         output_constraints: &Dimensions,
-    ) -> Dimensions {
+        fit_mode: FitMode,
+        stretch: f64,
+    ) -> Result<Dimensions> {
         let screen = Dimensions {
             height: output_constraints.height,
             width: output_constraints.width,
         };
-        Dimensions::fit_with_locked_ratio(image_dimensions, &screen)
+        // The image measured in cells: a cell covers `stretch` times more
+        // of the image height than of its width.
+        let image_dimensions = Dimensions {
+            height: image_dimensions.height,
+            width: ((image_dimensions.width as f64 * stretch).round() as usize).max(1),
+        };
+        Dimensions::fit(&image_dimensions, &screen, fit_mode)
     }
 
     fn maximize_contrast<'a>(
@@ -97,14 +592,492 @@ impl Converter {
         })
     }
 
-    fn maybe_maximize_contrast(&self, buffer: &mut Vec<u8>) {
-        if self.maximize_contrast {
+    /// Stretch the brightness of `buffer` to the whole range, unless it's
+    /// flat.
+    fn maybe_maximize_contrast(maximize_contrast: bool, buffer: &mut [u8]) {
+        if maximize_contrast {
             let pixels = buffer.iter();
             if let MinMaxResult::MinMax(&min, &max) = pixels.minmax() {
-                Self::maximize_contrast(
-                    buffer.iter_mut(), min, max,
-                )
+                if min < max {
+                    Self::maximize_contrast(
+                        buffer.iter_mut(), min, max,
+                    )
+                }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::converter::{
+        Converter, Cpixel, OutputMode, Binarize, Threshold, Filter, Reduction, TemporalDenoise,
+    };
+    use crate::{
+        Dimensions, FitMode, Rect, Rotation, Orientation, Buffer2d, ImageView, Yuv420, Error,
+        ConverterConfig, YuvSemiPlanar, Yuv420Planes, Plane, Yuv422, Yuv422Order, ColorRange,
+    };
+
+    fn image() -> Buffer2d<u8> {
+        // Two bright quadrants on a black 8x8 image.
+        let buffer = (0..64)
+            .map(|i| if (i / 8 < 4) == (i % 8 < 4) { 253 } else { 0 })
+            .collect();
+        Buffer2d::new(Dimensions { height: 8, width: 8 }, buffer)
+    }
+
+    #[test]
+    fn test_convert_into_matches_convert_one() {
+        let constraints = Dimensions { height: 2, width: 2 };
+        let mut converter = Converter::new(&constraints, &image().dimensions, false).unwrap();
+        let expected = converter.convert_one(&image()).unwrap();
+        assert_eq!(expected.buffer.iter().map(|c| c.0).collect::<String>(), "N  N");
+
+        let mut output = Buffer2d::default();
+        converter.convert_into(&image(), &mut output).unwrap();
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_convert_into_reuses_output() {
+        let constraints = Dimensions { height: 4, width: 4 };
+        let mut converter = Converter::new(&constraints, &image().dimensions, false).unwrap();
+        converter.set_output_mode(
+            OutputMode::Binary(Binarize::new(Threshold::AdaptiveMean { radius: 1, offset: 0 }))
+        );
+        let mut output = Buffer2d::default();
+        converter.convert_into(&image(), &mut output).unwrap();
+        let allocation = output.buffer.as_ptr();
+        for _ in 0..3 {
+            converter.convert_into(&image(), &mut output).unwrap();
+            assert_eq!(output.buffer.as_ptr(), allocation);
+        }
+        assert_eq!(output.dimensions, constraints);
+    }
+
+    #[test]
+    fn test_convert_padded_view() {
+        // Same image with every row padded to 11 elements.
+        let padded = image().buffer
+            .chunks(8)
+            .flat_map(|row| row.iter().copied().chain([7, 7, 7]))
+            .collect::<Vec<u8>>();
+        let view = ImageView::new(&padded, image().dimensions, 11, 1).unwrap();
+        let constraints = Dimensions { height: 2, width: 2 };
+        let mut converter = Converter::new(&constraints, &image().dimensions, false).unwrap();
+        let mut output = Buffer2d::default();
+        converter.convert_into(view, &mut output).unwrap();
+        assert_eq!(output, converter.convert_one(&image()).unwrap());
+    }
+
+    #[test]
+    fn test_crop() {
+        let mut converter = Converter::new(
+            &Dimensions { height: 4, width: 4 },
+            &image().dimensions,
+            false,
+        ).unwrap();
+        converter.set_crop(Some(Rect { x: 2, y: 0, height: 4, width: 4 }));
+        assert_eq!(converter.output_dimensions(), &Dimensions { height: 4, width: 4 });
+        let result = converter.convert_one(&image()).unwrap();
+        assert_eq!(result.buffer.iter().map(|c| c.0).collect::<String>(), "NN  NN  NN  NN  ");
+
+        // Wider than tall crops give wider than tall output.
+        converter.set_crop(Some(Rect { x: 0, y: 3, height: 2, width: 8 }));
+        assert_eq!(converter.output_dimensions(), &Dimensions { height: 1, width: 4 });
+    }
+
+    #[test]
+    fn test_zoom_and_pan_stay_inside() {
+        let mut converter = Converter::new(
+            &Dimensions { height: 4, width: 4 },
+            &image().dimensions,
+            false,
+        ).unwrap();
+        converter.zoom(2.);
+        assert_eq!(converter.crop(), Some(&Rect { x: 2, y: 2, height: 4, width: 4 }));
+        converter.pan(-10, 1);
+        assert_eq!(converter.crop(), Some(&Rect { x: 0, y: 3, height: 4, width: 4 }));
+        let result = converter.convert_one(&image()).unwrap();
+        assert_eq!(result.buffer.iter().map(|c| c.0).collect::<String>(), "NNNN            ");
+        converter.zoom(0.1);
+        assert_eq!(converter.crop(), Some(&Rect::full(&image().dimensions)));
+    }
+
+    #[test]
+    fn test_orientation() {
+        // Bright top half of a wide image.
+        let image = Buffer2d::new(
+            Dimensions { height: 4, width: 8 },
+            (0..32).map(|i| if i < 16 { 253 } else { 0 }).collect(),
+        );
+        let mut converter = Converter::new(
+            &Dimensions { height: 4, width: 4 },
+            &image.dimensions,
+            false,
+        ).unwrap();
+        assert_eq!(converter.output_dimensions(), &Dimensions { height: 2, width: 4 });
+
+        let quarter = Orientation { rotation: Rotation::Quarter, ..Default::default() };
+        converter.set_orientation(quarter);
+        assert_eq!(converter.output_dimensions(), &Dimensions { height: 4, width: 2 });
+        let result = converter.convert_one(&image).unwrap();
+        assert_eq!(result.buffer.iter().map(|c| c.0).collect::<String>(), " N N N N");
+
+        converter.set_orientation(Orientation {
+            rotation: Rotation::Quarter,
+            flip_horizontal: true,
+            flip_vertical: false,
+        });
+        let result = converter.convert_one(&image).unwrap();
+        assert_eq!(result.buffer.iter().map(|c| c.0).collect::<String>(), "N N N N ");
+
+        converter.set_orientation(Orientation { rotation: Rotation::Half, ..Default::default() });
+        let result = converter.convert_one(&image).unwrap();
+        assert_eq!(result.buffer.iter().map(|c| c.0).collect::<String>(), "    NNNN");
+    }
+
+    #[test]
+    fn test_fit_modes() {
+        // Bright left and right edges of a wide image, the center is black.
+        let image = Buffer2d::new(
+            Dimensions { height: 4, width: 8 },
+            (0..32).map(|i| if i % 8 < 2 || i % 8 >= 6 { 253 } else { 0 }).collect(),
+        );
+        let constraints = Dimensions { height: 4, width: 4 };
+        let mut converter = Converter::new(&constraints, &image.dimensions, false).unwrap();
+        let to_string = |result: Buffer2d<Cpixel>| {
+            result.buffer.iter().map(|c| c.0).collect::<String>()
+        };
+
+        converter.set_fit_mode(FitMode::Cover);
+        assert_eq!(converter.output_dimensions(), &constraints);
+        // Only the black center is left.
+        assert_eq!(to_string(converter.convert_one(&image).unwrap()), " ".repeat(16));
+
+        converter.set_fit_mode(FitMode::Stretch);
+        assert_eq!(converter.output_dimensions(), &constraints);
+        assert_eq!(to_string(converter.convert_one(&image).unwrap()), "N  N".repeat(4));
+
+        converter.set_fit_mode(FitMode::FixedHeight);
+        assert_eq!(converter.output_dimensions(), &Dimensions { height: 4, width: 8 });
+        converter.set_fit_mode(FitMode::FixedWidth);
+        assert_eq!(converter.output_dimensions(), &Dimensions { height: 2, width: 4 });
+    }
+
+    #[test]
+    fn test_cell_aspect_ratio() {
+        let image = Buffer2d::new(Dimensions { height: 8, width: 8 }, vec![0; 64]);
+        let constraints = Dimensions { height: 40, width: 40 };
+        let mut converter = Converter::new(&constraints, &image.dimensions, false).unwrap();
+        assert_eq!(converter.output_dimensions(), &Dimensions { height: 40, width: 40 });
+        // Cells twice as tall as wide, a square image takes half the rows.
+        converter.set_cell_aspect_ratio(2.).unwrap();
+        assert_eq!(converter.output_dimensions(), &Dimensions { height: 20, width: 40 });
+        let result = converter.convert_one(&image).unwrap();
+        assert_eq!(result.dimensions, Dimensions { height: 20, width: 40 });
+
+        // Cover keeps the constraints and crops the input to what they show.
+        converter.set_fit_mode(FitMode::Cover);
+        assert_eq!(converter.output_dimensions(), &constraints);
+        assert_eq!(converter.source, Some(Rect { x: 2, y: 0, height: 8, width: 4 }));
+    }
+
+    #[test]
+    fn test_sample_aspect_ratio() {
+        // Anamorphic frame stored at half its width.
+        let input = Dimensions { height: 8, width: 4 };
+        let constraints = Dimensions { height: 40, width: 40 };
+        let mut converter = Converter::new(&constraints, &input, false).unwrap();
+        converter.set_sample_aspect_ratio(2.).unwrap();
+        assert_eq!(converter.output_dimensions(), &Dimensions { height: 40, width: 40 });
+        converter.set_cell_aspect_ratio(2.).unwrap();
+        assert_eq!(converter.output_dimensions(), &Dimensions { height: 20, width: 40 });
+        // Rotated, the wide pixels become tall.
+        let quarter = Orientation { rotation: Rotation::Quarter, ..Default::default() };
+        converter.set_orientation(quarter);
+        assert_eq!(converter.output_dimensions(), &Dimensions { height: 20, width: 40 });
+        converter.set_cell_aspect_ratio(1.).unwrap();
+        assert_eq!(converter.output_dimensions(), &Dimensions { height: 40, width: 40 });
+    }
+
+    #[test]
+    fn test_yuv420_sample_aspect_ratio() {
+        let dimensions = Dimensions { height: 4, width: 2 };
+        let mut image = Yuv420::from_i420(dimensions, &[253; 12]).unwrap();
+        image.sample_aspect_ratio = Some(2.);
+        let constraints = Dimensions { height: 4, width: 4 };
+        let mut converter = Converter::new(&constraints, &dimensions, false).unwrap();
+        let mut output = Buffer2d::default();
+        converter.convert_yuv420_into(&image, &mut output).unwrap();
+        assert_eq!(converter.sample_aspect_ratio(), 2.);
+        assert_eq!(output.dimensions, Dimensions { height: 4, width: 4 });
+        assert_eq!(output.buffer.iter().map(|c| c.0).collect::<String>(), "N".repeat(16));
+        // Frames without a ratio keep the one of the converter.
+        image.sample_aspect_ratio = None;
+        converter.set_sample_aspect_ratio(4.).unwrap();
+        converter.convert_yuv420_into(&image, &mut output).unwrap();
+        assert_eq!(converter.sample_aspect_ratio(), 4.);
+        assert_eq!(output.dimensions, Dimensions { height: 2, width: 4 });
+    }
+
+    #[test]
+    fn test_semi_planar() {
+        let dimensions = Dimensions { height: 4, width: 2 };
+        let luma = [253, 253, 253, 253, 0, 0, 0, 0];
+        let frame = [&luma[..], &[128; 4]].concat();
+        let nv21 = YuvSemiPlanar::from_nv21(dimensions, &frame).unwrap();
+        let i420 = Yuv420::from_i420(dimensions, &frame).unwrap();
+        let mut converter = Converter::new(&dimensions, &dimensions, false).unwrap();
+        let (mut output, mut expected) = (Buffer2d::default(), Buffer2d::default());
+        converter.convert_semi_planar_into(&nv21, &mut output).unwrap();
+        converter.convert_yuv420_into(&i420, &mut expected).unwrap();
+        assert_eq!(output, expected);
+        assert_eq!(output.buffer[0], Cpixel('N'));
+
+        let short = YuvSemiPlanar::new(dimensions, &luma, &[128; 3], nv21.order);
+        assert_eq!(
+            converter.convert_semi_planar_into(&short, &mut output),
+            Err(Error::BufferLength { expected: 4, actual: 3 }),
+        );
+    }
+
+    #[test]
+    fn test_planes() {
+        let dimensions = Dimensions { height: 2, width: 3 };
+        let packed = [0, 128, 253, 253, 128, 0];
+        // Rows padded to 8 bytes.
+        let padded = [0, 128, 253, 9, 9, 9, 9, 9, 253, 128, 0];
+        let chroma = [128; 2];
+        let mut image = Yuv420Planes::new(
+            dimensions,
+            Plane::new(&padded, 8, 1),
+            Plane::new(&chroma, 2, 1),
+            Plane::new(&chroma, 2, 1),
+        );
+        image.sample_aspect_ratio = Some(2.);
+        let mut converter = Converter::new(&dimensions, &dimensions, false).unwrap();
+        let mut output = Buffer2d::default();
+        converter.convert_planes_into(&image, &mut output).unwrap();
+        assert_eq!(converter.sample_aspect_ratio(), 2.);
+        let mut expected = Buffer2d::default();
+        converter.convert_into(&Buffer2d::new(dimensions, packed.to_vec()), &mut expected).unwrap();
+        assert_eq!(output, expected);
+
+        image.u = Plane::new(&chroma, 2, 2);
+        assert_eq!(converter.convert_planes_into(&image, &mut output), Err(Error::InvalidLayout));
+    }
+
+    #[test]
+    fn test_yuv422() {
+        let dimensions = Dimensions { height: 2, width: 2 };
+        let luma = [0, 253, 253, 0];
+        let uyvy = [128, 0, 128, 253, 128, 253, 128, 0];
+        let mut image = Yuv422::packed(dimensions, &uyvy, Yuv422Order::Uyvy);
+        image.range = ColorRange::Full;
+        let mut converter = Converter::new(&dimensions, &dimensions, false).unwrap();
+        let (mut output, mut expected) = (Buffer2d::default(), Buffer2d::default());
+        converter.convert_yuv422_into(&image, &mut output).unwrap();
+        converter.convert_into(&Buffer2d::new(dimensions, luma.to_vec()), &mut expected).unwrap();
+        assert_eq!(output, expected);
+        let short = Yuv422::packed(dimensions, &uyvy[..7], Yuv422Order::Uyvy);
+        assert_eq!(converter.convert_yuv422_into(&short, &mut output), Err(Error::InvalidLayout));
+    }
+
+    #[test]
+    fn test_color_range() {
+        let dimensions = Dimensions { height: 2, width: 2 };
+        let full = Buffer2d::new(dimensions, vec![0, 128, 255, 255]);
+        let mut image = Yuv420::from_i420(dimensions, &[16, 126, 235, 250, 128, 128]).unwrap();
+        assert_eq!(image.range, ColorRange::Limited);
+        let mut converter = Converter::new(&dimensions, &dimensions, false).unwrap();
+        let (mut output, mut expected) = (Buffer2d::default(), Buffer2d::default());
+        converter.convert_yuv420_into(&image, &mut output).unwrap();
+        converter.convert_into(&full, &mut expected).unwrap();
+        assert_eq!(output, expected);
+
+        image.range = ColorRange::Full;
+        converter.convert_yuv420_into(&image, &mut output).unwrap();
+        assert_ne!(output, expected);
+    }
+
+    #[test]
+    fn test_letterbox() {
+        let image = Buffer2d::new(Dimensions { height: 2, width: 4 }, vec![253; 8]);
+        let constraints = Dimensions { height: 4, width: 4 };
+        let mut converter = Converter::new(&constraints, &image.dimensions, false).unwrap();
+        converter.set_letterbox(Some('.'));
+        assert_eq!(converter.output_dimensions(), &Dimensions { height: 2, width: 4 });
+        let mut output = Buffer2d::default();
+        converter.convert_into(&image, &mut output).unwrap();
+        assert_eq!(output.dimensions, constraints);
+        assert_eq!(output.buffer.iter().map(|c| c.0).collect::<String>(), "....NNNNNNNN....");
+
+        // Images larger than the constraints are cut around their center.
+        converter.set_fit_mode(FitMode::FixedHeight);
+        converter.convert_into(&image, &mut output).unwrap();
+        assert_eq!(output.dimensions, constraints);
+        assert_eq!(output.buffer.iter().map(|c| c.0).collect::<String>(), "N".repeat(16));
+
+        converter.set_fit_mode(FitMode::Contain);
+        converter.set_letterbox(None);
+        converter.convert_into(&image, &mut output).unwrap();
+        assert_eq!(output.dimensions, Dimensions { height: 2, width: 4 });
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_matches_single_thread() {
+        use crate::{Filter, Reduction, TemporalDenoise};
+
+        let dimensions = Dimensions { height: 211, width: 307 };
+        let frames = (0..3_u32)
+            .map(|frame| {
+                let buffer = (0..dimensions.total() as u32)
+                    .map(|i| (i.wrapping_mul(2_654_435_761).rotate_left(frame + 7) >> 24) as u8)
+                    .collect();
+                Buffer2d::new(dimensions, buffer)
+            })
+            .collect::<Vec<_>>();
+        let convert = || {
+            let constraints = Dimensions { height: 50, width: 90 };
+            let mut converter =
+                Converter::with_filter(&constraints, &dimensions, false, Filter::Lanczos3).unwrap();
+            converter.set_temporal_denoise(Some(TemporalDenoise::new(0.5, 40)));
+            converter.set_reduction(Reduction::Median);
+            converter.set_output_mode(OutputMode::Binary(Binarize::new(Threshold::Otsu)));
+            let mut results = frames.iter()
+                .map(|frame| converter.convert_one(frame).unwrap())
+                .collect::<Vec<_>>();
+            converter.set_output_mode(OutputMode::Binary(
+                Binarize::new(Threshold::AdaptiveGaussian { radius: 3, offset: 2 })
+            ));
+            results.extend(frames.iter().map(|frame| converter.convert_one(frame).unwrap()));
+
+            // Area resampling by a non-integer ratio, then the SIMD glyph
+            // lookup.
+            let mut converter = Converter::new(&constraints, &dimensions, false).unwrap();
+            assert_eq!(converter.output_mode(), &OutputMode::Grayscale);
+            assert!(dimensions.width % converter.output_dimensions().width != 0);
+            results.extend(frames.iter().map(|frame| converter.convert_one(frame).unwrap()));
+            converter.set_temporal_denoise(Some(TemporalDenoise::new(0.5, 40)));
+            results.extend(frames.iter().map(|frame| converter.convert_one(frame).unwrap()));
+            results
+        };
+        let single = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap()
+            .install(convert);
+        let many = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap()
+            .install(convert);
+        assert_eq!(single, many);
+    }
+
+    #[test]
+    fn test_errors() {
+        let constraints = Dimensions { height: 4, width: 4 };
+        let empty = Dimensions { height: 0, width: 8 };
+        assert_eq!(
+            Converter::new(&constraints, &empty, false).err(),
+            Some(Error::EmptyDimensions(empty)),
+        );
+        assert!(Converter::new(&empty, &image().dimensions, false).is_err());
+
+        let mut converter = Converter::new(&constraints, &image().dimensions, false).unwrap();
+        let small = Buffer2d::new(Dimensions { height: 4, width: 8 }, vec![0; 32]);
+        assert_eq!(
+            converter.convert_one(&small),
+            Err(Error::DimensionsMismatch {
+                expected: image().dimensions,
+                actual: small.dimensions,
+            }),
+        );
+        let short = Buffer2d::new(image().dimensions, vec![0; 10]);
+        assert_eq!(
+            converter.convert_one(&short),
+            Err(Error::BufferLength { expected: 64, actual: 10 }),
+        );
+        let mut output = Buffer2d::default();
+        assert_eq!(
+            converter.convert_into(&short, &mut output),
+            Err(Error::BufferLength { expected: 64, actual: 10 }),
+        );
+        assert_eq!(converter.set_cell_aspect_ratio(0.), Err(Error::InvalidRatio(0.)));
+        assert!(converter.set_sample_aspect_ratio(f64::NAN).is_err());
+        // The converter still works after errors.
+        assert!(converter.convert_one(&image()).is_ok());
+    }
+
+    #[test]
+    fn test_maximize_contrast() {
+        // Dim left half, slightly brighter right half.
+        let dimensions = Dimensions { height: 2, width: 4 };
+        let image = Buffer2d::new(dimensions, vec![100, 100, 120, 120, 100, 100, 120, 120]);
+        let mut converter = Converter::new(&dimensions, &dimensions, false).unwrap();
+        let flat = converter.convert_one(&image).unwrap();
+        assert!(flat.buffer.iter().all(|&c| c == Cpixel::from_brightness(100)
+            || c == Cpixel::from_brightness(120)));
+
+        converter.apply_config(&ConverterConfig {
+            maximize_contrast: true,
+            ..converter.config()
+        }).unwrap();
+        let stretched = converter.convert_one(&image).unwrap();
+        assert_eq!(stretched.row(0), &[
+            Cpixel::from_brightness(0), Cpixel::from_brightness(0),
+            Cpixel::from_brightness(255), Cpixel::from_brightness(255),
+        ]);
+        assert_ne!(stretched, flat);
+        // A flat frame is left as is.
+        let gray = Buffer2d::new(dimensions, vec![120; 8]);
+        let output = converter.convert_one(&gray).unwrap();
+        assert!(output.buffer.iter().all(|&c| c == Cpixel::from_brightness(120)));
+    }
+
+    #[test]
+    fn test_maximize_contrast_after_denoise() {
+        let dimensions = Dimensions { height: 1, width: 4 };
+        let frames = [vec![100, 100, 120, 120], vec![96, 100, 120, 124]];
+        let mut converter = Converter::new(&dimensions, &dimensions, true).unwrap();
+        converter.set_temporal_denoise(Some(TemporalDenoise::new(0.75, 32)));
+        let mut denoise = TemporalDenoise::new(0.75, 32);
+        for frame in frames {
+            let mut expected = Buffer2d::new(dimensions, frame);
+            let output = converter.convert_one(&expected).unwrap();
+            denoise.apply(&mut expected);
+            Converter::maybe_maximize_contrast(true, &mut expected.buffer);
+            assert_eq!(output, expected.map(|&n| Cpixel::from_brightness(n)));
+        }
+    }
+
+    #[test]
+    fn test_config() {
+        let constraints = Dimensions { height: 8, width: 8 };
+        let config = ConverterConfig {
+            output_mode: OutputMode::Binary(Binarize::new(Threshold::Otsu)),
+            fit_mode: FitMode::Stretch,
+            filter: Filter::Nearest,
+            reduction: Reduction::Max,
+            cell_aspect_ratio: 2.,
+            letterbox: Some('.'),
+            temporal_denoise: Some(TemporalDenoise::new(0.5, 20)),
+            ..ConverterConfig::default()
+        };
+        let mut converter = Converter::with_config(&constraints, &image().dimensions, &config)
+            .unwrap();
+        assert_eq!(converter.config(), config);
+        assert_eq!(converter.temporal_denoise().map(|d| d.motion_threshold()), Some(20));
+        assert_eq!(converter.filter(), Filter::Nearest);
+        assert_eq!(converter.output_dimensions(), &constraints);
+
+        let invalid = ConverterConfig { cell_aspect_ratio: -1., ..ConverterConfig::default() };
+        assert_eq!(converter.apply_config(&invalid), Err(Error::InvalidRatio(-1.)));
+        assert_eq!(converter.config(), config);
+        converter.apply_config(&ConverterConfig::default()).unwrap();
+        assert_eq!(converter.config(), ConverterConfig::default());
+    }
+}
@@ -1,83 +1,130 @@
-use crate::{Dimensions, Buffer2d};
-use std::cmp::Ordering;
 use std::convert::TryInto;
+use crate::{Dimensions, Buffer2d, ImageView, Error, Result};
+use crate::parallel::{for_each_row, for_each_row_with};
+use weights::AxisWeights;
+pub use weights::Filter;
+pub use reduction::Reduction;
+pub use sample::{Sample, Channel};
 
-pub struct Scale {
-    grow_buffer: Buffer2d<usize>,
-    shrink_buffer: Buffer2d<usize>,
+mod weights;
+mod reduction;
+mod sample;
+mod box_shrink;
+
+/// Separable resampler between two fixed dimensions, for any [`Sample`]
+/// type. Channels are resampled independently.
+///
+/// Depending on `filter`, axes that shrink either average the source
+/// elements each destination element covers, weighted by the covered
+/// fraction, or convolve with the widened kernel. Axes that grow
+/// interpolate with `filter`.
+///
+/// Any `reduction` but the mean replaces the shrinking part: every
+/// destination element is reduced from the source elements it overlaps,
+/// then the growing axes are interpolated.
+pub struct Scale<T: Sample = u8> {
+    x_weights: AxisWeights,
+    y_weights: AxisWeights,
+    rows: Vec<<T::Channel as Channel>::Accumulator>,
+    filter: Filter,
+    reduction: Reduction,
+    needs_reduce: bool,
+    reduce_x_weights: AxisWeights,
+    reduce_y_weights: AxisWeights,
+    /// Values and areas under a destination element, per row band.
+    reduce_scratch: Vec<(Vec<T::Channel>, Vec<i64>)>,
+    reduced_buffer: Buffer2d<T>,
+    /// Whole shrink factors when the mean of blocks is all that's needed.
+    box_factors: Option<(usize, usize)>,
+    /// Column sums of the box fast path, per row band.
+    box_sums: Vec<Vec<u16>>,
     from_dimensions: Dimensions,
     to_dimensions: Dimensions,
-    needs_grow: bool,
-    needs_shrink: bool,
 }
 
-impl Scale {
+impl<T: Sample> Scale<T> {
+    #[allow(dead_code)]
     pub fn new(from_dimensions: &Dimensions, to_dimensions: &Dimensions) -> Self {
-        let mut shrink_width = from_dimensions.width;
-        let mut shrink_height = from_dimensions.height;
-        let mut grow_width = from_dimensions.width;
-        let mut grow_height = from_dimensions.height;
-        let mut needs_grow;
-        let mut needs_shrink;
-
-        match to_dimensions.height.cmp(&from_dimensions.height) {
-            Ordering::Less => {
-                shrink_height = to_dimensions.height;
-                grow_height = (from_dimensions.height as f64 / to_dimensions.height as f64).ceil() as usize * to_dimensions.height;
-                if grow_height == from_dimensions.height {
-                    needs_grow = false;
-                } else {
-                    needs_grow = true;
-                }
-                needs_shrink = true;
-            }
-            Ordering::Greater => {
-                grow_height = to_dimensions.height;
-                shrink_height = to_dimensions.height;
-                needs_shrink = false;
-                needs_grow = true;
-            }
-            Ordering::Equal => {
-                needs_grow = false;
-                needs_shrink = false;
-            }
+        Self::with_filter(from_dimensions, to_dimensions, Filter::default())
+    }
+
+    pub fn with_filter(
+        from_dimensions: &Dimensions,
+        to_dimensions: &Dimensions,
+        filter: Filter,
+    ) -> Self {
+        Self::with_reduction(from_dimensions, to_dimensions, filter, Reduction::default())
+    }
+
+    pub fn with_reduction(
+        from_dimensions: &Dimensions,
+        to_dimensions: &Dimensions,
+        filter: Filter,
+        reduction: Reduction,
+    ) -> Self {
+        let needs_reduce = reduction != Reduction::Mean
+            && (to_dimensions.height < from_dimensions.height
+            || to_dimensions.width < from_dimensions.width);
+        let reduce_weights = |initial_n: usize, final_n: usize| {
+            AxisWeights::area(initial_n, final_n.min(initial_n))
+        };
+        let (reduce_x_weights, reduce_y_weights, reduced_dimensions) = if needs_reduce {
+            (
+                reduce_weights(from_dimensions.width, to_dimensions.width),
+                reduce_weights(from_dimensions.height, to_dimensions.height),
+                Dimensions {
+                    height: from_dimensions.height.min(to_dimensions.height),
+                    width: from_dimensions.width.min(to_dimensions.width),
+                },
+            )
+        } else {
+            (AxisWeights::default(), AxisWeights::default(), *from_dimensions)
         };
 
-        match to_dimensions.width.cmp(&from_dimensions.width) {
-            Ordering::Less => {
-                shrink_width = to_dimensions.width;
-                grow_width = (from_dimensions.width as f64 / to_dimensions.width as f64).ceil() as usize * to_dimensions.width;
-                if grow_width != from_dimensions.width {
-                    needs_grow = true;
-                }
-                needs_shrink = true;
-            }
-            Ordering::Greater => {
-                grow_width = to_dimensions.width;
-                shrink_width = to_dimensions.width;
-                needs_grow = true;
+        let axis_weights = |initial_n: usize, final_n: usize| {
+            if final_n < initial_n && filter.shrinks_by_area() {
+                AxisWeights::area(initial_n, final_n)
+            } else {
+                AxisWeights::new(initial_n, final_n, filter)
             }
-            Ordering::Equal => {}
         };
-
-        let grow_buffer = if needs_grow {
-            Buffer2d::default_with_dimensions(&Dimensions { height: grow_height, width: grow_width })
-        } else {
-            Buffer2d::default()
+        let shrinks_by_whole = |initial_n: usize, final_n: usize| {
+            final_n > 0 && final_n <= initial_n && initial_n % final_n == 0
         };
-        let shrink_buffer = if needs_shrink {
-            Buffer2d::default_with_dimensions(&Dimensions { height: shrink_height, width: shrink_width })
+        let box_factors = if filter.shrinks_by_area()
+            && reduction == Reduction::Mean
+            && from_dimensions != to_dimensions
+            && shrinks_by_whole(from_dimensions.width, to_dimensions.width)
+            && shrinks_by_whole(from_dimensions.height, to_dimensions.height) {
+            Some((
+                from_dimensions.width / to_dimensions.width,
+                from_dimensions.height / to_dimensions.height,
+            ))
         } else {
-            Buffer2d::default()
+            None
         };
-
         Self {
-            grow_buffer,
-            shrink_buffer,
+            x_weights: axis_weights(reduced_dimensions.width, to_dimensions.width),
+            y_weights: axis_weights(reduced_dimensions.height, to_dimensions.height),
+            rows: vec![
+                Default::default();
+                reduced_dimensions.height * to_dimensions.width * T::CHANNELS
+            ],
+            filter,
+            reduction,
+            needs_reduce,
+            reduce_x_weights,
+            reduce_y_weights,
+            reduce_scratch: Vec::new(),
+            reduced_buffer: if needs_reduce {
+                Buffer2d::default_with_dimensions(&reduced_dimensions)
+            } else {
+                Buffer2d::default()
+            },
+            box_factors,
+            box_sums: Vec::new(),
             from_dimensions: from_dimensions.to_owned(),
             to_dimensions: to_dimensions.to_owned(),
-            needs_grow,
-            needs_shrink,
         }
     }
 
@@ -91,154 +138,201 @@ impl Scale {
         self.to_dimensions
     }
 
-    pub fn resize<T: Into<u8> + Clone>(
-        &mut self,
-        buf: &Buffer2d<T>,
-    ) -> Buffer2d<u8>
+    #[allow(dead_code)]
+    pub fn get_filter(&self) -> Filter {
+        self.filter
+    }
+
+    #[allow(dead_code)]
+    pub fn get_reduction(&self) -> Reduction {
+        self.reduction
+    }
+
+    /// `DimensionsMismatch` if `image` doesn't have the dimensions the scale
+    /// was built for, `BufferLength` if it's a `Buffer2d` shorter than them.
+    pub fn resize<'a, I>(&mut self, image: I) -> Result<Buffer2d<T>>
+        where T: 'a, I: TryInto<ImageView<'a, T>>, Error: From<I::Error>
     {
-        let mut buffer: &Buffer2d<usize> = &Buffer2d {
-            buffer: buf.buffer.iter().map(|x| x.clone().into().into()).collect(),
-            dimensions: buf.dimensions,
-        };
+        let mut output = Buffer2d::default();
+        self.resize_into(image, &mut output)?;
+        Ok(output)
+    }
 
-        if self.needs_grow {
-            buffer = Self::grow(buffer, &mut self.grow_buffer);
+    /// Like `resize`, reusing the allocation of `output`. `image` can be any
+    /// strided view, it's read in place.
+    pub fn resize_into<'a, I>(
+        &mut self,
+        image: I,
+        output: &mut Buffer2d<T>,
+    ) -> Result<()> where T: 'a, I: TryInto<ImageView<'a, T>>, Error: From<I::Error> {
+        let image = image.try_into()?;
+        if image.dimensions() != self.from_dimensions {
+            return Err(Error::DimensionsMismatch {
+                expected: self.from_dimensions,
+                actual: image.dimensions(),
+            });
         }
-        if self.needs_shrink {
-            buffer = Self::shrink(buffer, &mut self.shrink_buffer);
+        if let Some(factors) = self.box_factors {
+            output.buffer.clear();
+            output.buffer.resize(self.to_dimensions.total(), T::default());
+            output.dimensions = self.to_dimensions;
+            if T::box_shrink(image, factors, &mut self.box_sums, &mut output.buffer) {
+                return Ok(());
+            }
         }
-        Buffer2d {
-            buffer: buffer.buffer.iter().map(|&n| n.try_into().unwrap()).collect(),
-            dimensions: self.to_dimensions,
+        let image = if self.needs_reduce {
+            Self::reduce(
+                image,
+                self.reduction,
+                &self.reduce_x_weights,
+                &self.reduce_y_weights,
+                &mut self.reduce_scratch,
+                &mut self.reduced_buffer,
+            );
+            self.reduced_buffer.view()
+        } else {
+            image
+        };
+
+        output.buffer.clear();
+        output.dimensions = self.to_dimensions;
+        if image.dimensions() == self.to_dimensions {
+            output.buffer.extend(image.iter().copied());
+        } else {
+            output.buffer.resize(self.to_dimensions.total(), T::default());
+            Self::resample(image, &self.x_weights, &self.y_weights, &mut self.rows, output);
         }
+        Ok(())
     }
 
-    fn shrink<'a>(
-        buffer_to_resize: &Buffer2d<usize>,
-        inner_buffer: &'a mut Buffer2d<usize>,
-    ) -> &'a Buffer2d<usize> {
-        inner_buffer.buffer.fill(0);
-        let y_area = buffer_to_resize.dimensions.height / inner_buffer.dimensions.height;
-        let x_area = buffer_to_resize.dimensions.width / inner_buffer.dimensions.width;
-        let mut buf = buffer_to_resize.buffer.iter();
-        for inner_row in inner_buffer.buffer.chunks_exact_mut(inner_buffer.dimensions.width) {
-            for _ in 0..y_area {
-                for inner_elem in inner_row.iter_mut() {
-                    for _ in 0..x_area {
-                        *inner_elem += buf.next().unwrap().clone();
+    /// Apply `reduction` to the source elements under every destination
+    /// element, as given by the area weights.
+    fn reduce(
+        image: ImageView<'_, T>,
+        reduction: Reduction,
+        x_weights: &AxisWeights,
+        y_weights: &AxisWeights,
+        scratch: &mut Vec<(Vec<T::Channel>, Vec<i64>)>,
+        inner_buffer: &mut Buffer2d<T>,
+    ) {
+        let width = inner_buffer.dimensions.width;
+        for_each_row_with(
+            &mut inner_buffer.buffer,
+            width,
+            scratch,
+            |(values, areas), y, inner_row| {
+                let y_taps = &y_weights.taps[y];
+                for (inner_elem, x_taps) in inner_row.iter_mut().zip(x_weights.taps.iter()) {
+                    areas.clear();
+                    for &y_area in y_taps.weights.iter() {
+                        areas.extend(
+                            x_taps.weights.iter().map(|&x_area| y_area as i64 * x_area as i64)
+                        );
+                    }
+                    let columns = x_taps.start..x_taps.start + x_taps.weights.len();
+                    for channel in 0..T::CHANNELS {
+                        values.clear();
+                        for y in y_taps.start..y_taps.start + y_taps.weights.len() {
+                            let row = image.row(y);
+                            values.extend(columns.clone().map(|x| row[x].channel(channel)));
+                        }
+                        inner_elem.set_channel(channel, reduction.reduce(values, areas));
                     }
                 }
-            }
-        }
-
-        inner_buffer.buffer.iter_mut().for_each(|elem| *elem = *elem / (x_area * y_area));
-        inner_buffer
-    }
-
-    fn grow<'a>(
-        buffer_to_resize: &Buffer2d<usize>,
-        inner_buffer: &'a mut Buffer2d<usize>,
-    ) -> &'a Buffer2d<usize> {
-        let elements_to_interpolate_in_y = Self::elements_to_interpolate(
-            buffer_to_resize.dimensions.height,
-            inner_buffer.dimensions.height,
-        );
-        let elements_to_interpolate_in_x = Self::elements_to_interpolate(
-            buffer_to_resize.dimensions.width,
-            inner_buffer.dimensions.width,
+            },
         );
+    }
 
-        let mut buffer_to_resize_chunked = buffer_to_resize.buffer
-            .chunks_exact(buffer_to_resize.dimensions.width);
-        let inner_buffer_indices = (0..inner_buffer.dimensions.total())
-            .step_by(inner_buffer.dimensions.width);
-        let zip_inner_buffer_indices_should_interpolate =
-            inner_buffer_indices.zip(elements_to_interpolate_in_y.iter());
-
-        for (index, &should_interpolate_row) in zip_inner_buffer_indices_should_interpolate {
-            let inner_buffer_width = inner_buffer.dimensions.width;
-            if should_interpolate_row {
-                inner_buffer.buffer
-                    .copy_within((index - inner_buffer_width)..index, index);
-            } else {
-                let mut col = buffer_to_resize_chunked.next().unwrap().iter();
-                let zip_index_and_should_interpolate =
-                    (index..index + inner_buffer_width).zip(elements_to_interpolate_in_x.iter());
-
-                for (index, &should_interpolate_col) in zip_index_and_should_interpolate {
-                    if should_interpolate_col {
-                        inner_buffer.buffer[index] = inner_buffer.buffer[index - 1].clone();
-                    } else {
-                        inner_buffer.buffer[index] = col.next().unwrap().clone();
+    /// Separable resampling, rows first, using the precomputed weights.
+    ///
+    /// Sums stay unnormalized between the two passes so integer ratios
+    /// produce exact averages.
+    fn resample(
+        image: ImageView<'_, T>,
+        x_weights: &AxisWeights,
+        y_weights: &AxisWeights,
+        rows: &mut [<T::Channel as Channel>::Accumulator],
+        inner_buffer: &mut Buffer2d<T>,
+    ) {
+        let channels = T::CHANNELS;
+        let inner_width = inner_buffer.dimensions.width;
+        let row_len = inner_width * channels;
+        for_each_row(rows, row_len, |y, row| {
+            let source_row = image.row(y);
+            for (elem, taps) in row.chunks_exact_mut(channels).zip(x_weights.taps.iter()) {
+                for (channel, elem) in elem.iter_mut().enumerate() {
+                    let mut sum = Default::default();
+                    for (x, &w) in (taps.start..).zip(taps.weights.iter()) {
+                        sum += source_row[x].channel(channel).widen() * T::Channel::weight(w as i64);
                     }
+                    *elem = sum;
                 }
             }
-        }
-        inner_buffer
-    }
-
-    fn elements_to_interpolate(
-        initial_n: usize,
-        final_n: usize,
-    ) -> Vec<bool> {
-        let stride = Self::calc_grow_stride(initial_n, final_n);
-        let new_elems = (1..=final_n - initial_n).into_iter();
-        let mut result = vec![false; final_n];
-        new_elems
-            .map(|elem| (elem as f64 * stride).ceil() as usize - 1)
-            .for_each(|index| result[index] = true);
-        result
-    }
+        });
 
-    fn calc_grow_stride(initial_n: usize, final_n: usize) -> f64 {
-        let delta = final_n - initial_n;
-        final_n as f64 / delta as f64
+        let rows = &*rows;
+        let denominator = x_weights.denominator * y_weights.denominator;
+        for_each_row(&mut inner_buffer.buffer, inner_width, |y, inner_row| {
+            let taps = &y_weights.taps[y];
+            for (x, inner_elem) in inner_row.iter_mut().enumerate() {
+                for channel in 0..channels {
+                    let mut sum = Default::default();
+                    let column = rows[taps.start * row_len + x * channels + channel..]
+                        .iter()
+                        .step_by(row_len);
+                    for (&n, &w) in column.zip(taps.weights.iter()) {
+                        sum += n * T::Channel::weight(w as i64);
+                    }
+                    inner_elem.set_channel(channel, T::Channel::narrow(sum, denominator));
+                }
+            }
+        });
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::converter::scale::Scale;
-    use crate::{Dimensions, Buffer2d};
+    use crate::converter::scale::{Scale, Filter, Reduction};
+    use crate::{Dimensions, Buffer2d, ImageView, Rgb, Error};
 
     #[test]
     fn test_grow_all_same() {
-        let mut scale = Scale::new(&Dimensions { height: 5, width: 5 },
+        let mut scale = Scale::<u8>::new(&Dimensions { height: 5, width: 5 },
                                    &Dimensions { height: 10, width: 10 });
         let result = scale.resize(
             &Buffer2d::new(Dimensions { height: 5, width: 5 }, vec![35; 25])
-        );
+        ).unwrap();
         assert_eq!(result.dimensions, Dimensions { height: 10, width: 10 });
         assert_eq!(result.buffer, vec![35; 100]);
     }
 
     #[test]
     fn test_from_different_values() {
-        let mut scale = Scale::new(&Dimensions { height: 5, width: 2 },
+        let mut scale = Scale::<u8>::new(&Dimensions { height: 5, width: 2 },
                                    &Dimensions { height: 6, width: 1 });
         let result = scale.resize(
             &Buffer2d::new(
                 Dimensions { height: 5, width: 2 },
                 vec![10, 0, 8, 9, 6, 100, 97, 89, 34, 98],
             )
-        );
+        ).unwrap();
         assert_eq!(result.dimensions, Dimensions { height: 6, width: 1 });
         assert_eq!(
             result.buffer,
-            vec![5, 8, 53, 93, 66, 66]
+            vec![5, 9, 53, 93, 66, 66]
         );
     }
 
     #[test]
     fn test_from_different_values_one_row() {
-        let mut scale = Scale::new(&Dimensions { height: 5, width: 2 },
+        let mut scale = Scale::<u8>::new(&Dimensions { height: 5, width: 2 },
                                    &Dimensions { height: 6, width: 2 });
         let result = scale.resize(
             &Buffer2d::new(
                 Dimensions { height: 5, width: 2 },
                 vec![10, 0, 8, 9, 6, 1, 2, 3, 4, 9],
             )
-        );
+        ).unwrap();
         assert_eq!(result.dimensions, Dimensions { height: 6, width: 2 });
         assert_eq!(
             result.buffer,
@@ -248,14 +342,14 @@ mod tests {
 
     #[test]
     fn test_more_than_one_interpolation_at_position() {
-        let mut scale = Scale::new(&Dimensions { height: 1, width: 1 },
+        let mut scale = Scale::<u8>::new(&Dimensions { height: 1, width: 1 },
                                    &Dimensions { height: 3, width: 3 });
         let result = scale.resize(
             &Buffer2d::new(
                 Dimensions { height: 1, width: 1 },
                 vec![1],
             )
-        );
+        ).unwrap();
         assert_eq!(result.dimensions, Dimensions { height: 3, width: 3 });
         assert_eq!(
             result.buffer,
@@ -265,40 +359,293 @@ mod tests {
 
     #[test]
     fn test_shrink_same() {
-        let mut scale = Scale::new(&Dimensions { height: 10, width: 10 },
+        let mut scale = Scale::<u8>::new(&Dimensions { height: 10, width: 10 },
                                    &Dimensions { height: 5, width: 5 });
         let result = scale.resize(
             &Buffer2d::new(Dimensions { height: 10, width: 10 }, vec![35; 100])
-        );
+        ).unwrap();
         assert_eq!(result.dimensions, Dimensions { height: 5, width: 5 });
         assert_eq!(result.buffer, vec![35; 25]);
     }
 
     #[test]
     fn test_shrink_different_values() {
-        let mut scale = Scale::new(&Dimensions { height: 4, width: 4 },
+        let mut scale = Scale::<u8>::new(&Dimensions { height: 4, width: 4 },
                                    &Dimensions { height: 2, width: 2 });
         let result = scale.resize(
             &Buffer2d::new(Dimensions { height: 4, width: 4 }, vec![8, 4, 4, 8, 8, 4, 4, 8, 8, 4, 4, 8, 8, 4, 4, 8])
-        );
+        ).unwrap();
         assert_eq!(result.dimensions, Dimensions { height: 2, width: 2 });
         assert_eq!(result.buffer, vec![6; 4]);
     }
 
     #[test]
     fn test_grow_shrink() {
-        let mut scale = Scale::new(&Dimensions { height: 2, width: 5 },
+        let mut scale = Scale::<u8>::new(&Dimensions { height: 2, width: 5 },
                                    &Dimensions { height: 1, width: 6 });
         let result = scale.resize(
             &Buffer2d::new(
                 Dimensions { height: 2, width: 5 },
                 vec![10, 0, 8, 9, 6, 100, 97, 89, 34, 98],
             )
-        );
+        ).unwrap();
         assert_eq!(result.dimensions, Dimensions { height: 1, width: 6 });
         assert_eq!(
             result.buffer,
-            vec![55, 48, 48, 21, 52, 52]
+            vec![55, 49, 49, 22, 52, 52]
+        );
+    }
+
+    #[test]
+    fn test_shrink_fractional_ratio() {
+        let mut scale = Scale::<u8>::new(&Dimensions { height: 1, width: 5 },
+                                   &Dimensions { height: 1, width: 2 });
+        let result = scale.resize(
+            &Buffer2d::new(Dimensions { height: 1, width: 5 }, vec![10, 20, 30, 40, 50])
+        ).unwrap();
+        assert_eq!(result.dimensions, Dimensions { height: 1, width: 2 });
+        assert_eq!(result.buffer, vec![18, 42]);
+    }
+
+    #[test]
+    fn test_shrink_fractional_ratio_both_axes() {
+        let mut scale = Scale::<u8>::new(&Dimensions { height: 3, width: 3 },
+                                   &Dimensions { height: 2, width: 2 });
+        let result = scale.resize(
+            &Buffer2d::new(
+                Dimensions { height: 3, width: 3 },
+                vec![0, 0, 90, 0, 0, 90, 90, 90, 90],
+            )
+        ).unwrap();
+        assert_eq!(result.buffer, vec![0, 60, 60, 80]);
+    }
+
+    #[test]
+    fn test_bilinear_grow() {
+        let mut scale = Scale::<u8>::with_filter(&Dimensions { height: 1, width: 2 },
+                                           &Dimensions { height: 1, width: 4 },
+                                           Filter::Bilinear);
+        let result = scale.resize(
+            &Buffer2d::new(Dimensions { height: 1, width: 2 }, vec![0, 100])
+        ).unwrap();
+        assert_eq!(result.dimensions, Dimensions { height: 1, width: 4 });
+        assert_eq!(result.buffer, vec![0, 25, 75, 100]);
+    }
+
+    #[test]
+    fn test_bilinear_grow_both_axes() {
+        let mut scale = Scale::<u8>::with_filter(&Dimensions { height: 2, width: 2 },
+                                           &Dimensions { height: 4, width: 4 },
+                                           Filter::Bilinear);
+        let result = scale.resize(
+            &Buffer2d::new(Dimensions { height: 2, width: 2 }, vec![0, 0, 160, 160])
+        ).unwrap();
+        assert_eq!(
+            result.buffer,
+            vec![0, 0, 0, 0, 40, 40, 40, 40, 120, 120, 120, 120, 160, 160, 160, 160]
+        );
+    }
+
+    #[test]
+    fn test_bicubic_grow_is_clamped() {
+        let mut scale = Scale::<u8>::with_filter(&Dimensions { height: 1, width: 4 },
+                                           &Dimensions { height: 1, width: 8 },
+                                           Filter::Bicubic);
+        let result = scale.resize(
+            &Buffer2d::new(Dimensions { height: 1, width: 4 }, vec![0, 0, 255, 255])
+        ).unwrap();
+        assert_eq!(result.buffer, vec![0, 0, 0, 58, 197, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_lanczos_shrink_all_same() {
+        let mut scale = Scale::<u8>::with_filter(&Dimensions { height: 30, width: 41 },
+                                           &Dimensions { height: 7, width: 9 },
+                                           Filter::Lanczos3);
+        let result = scale.resize(
+            &Buffer2d::new(Dimensions { height: 30, width: 41 }, vec![200; 30 * 41])
+        ).unwrap();
+        assert_eq!(result.buffer, vec![200; 63]);
+    }
+
+    #[test]
+    fn test_gaussian_shrink_removes_aliasing() {
+        // Columns alternating at the source frequency must not beat into a
+        // pattern when shrunk by a non-integer factor.
+        let source = (0..60).map(|x| if x % 2 == 0 { 0 } else { 200 }).collect();
+        let mut scale = Scale::<u8>::with_filter(&Dimensions { height: 1, width: 60 },
+                                           &Dimensions { height: 1, width: 13 },
+                                           Filter::Gaussian);
+        let result = scale.resize(
+            &Buffer2d::new(Dimensions { height: 1, width: 60 }, source)
+        ).unwrap();
+        // The borders repeat the edge column.
+        let interior = &result.buffer[1..12];
+        assert!(interior.iter().all(|&n| (95..=105).contains(&n)));
+    }
+
+    #[test]
+    fn test_bicubic_grow_all_same() {
+        let mut scale = Scale::<u8>::with_filter(&Dimensions { height: 3, width: 5 },
+                                           &Dimensions { height: 7, width: 9 },
+                                           Filter::Bicubic);
+        let result = scale.resize(
+            &Buffer2d::new(Dimensions { height: 3, width: 5 }, vec![35; 15])
+        ).unwrap();
+        assert_eq!(result.buffer, vec![35; 63]);
+    }
+
+    #[test]
+    fn test_min_keeps_thin_dark_line() {
+        let mut source = vec![200; 36];
+        source[2 * 6..3 * 6].fill(0);
+        let input = Buffer2d::new(Dimensions { height: 6, width: 6 }, source);
+        let to = Dimensions { height: 2, width: 3 };
+
+        let mut mean = Scale::<u8>::new(&input.dimensions, &to);
+        assert_eq!(mean.resize(&input).unwrap().buffer, vec![133, 133, 133, 200, 200, 200]);
+
+        let mut min = Scale::<u8>::with_reduction(&input.dimensions, &to, Filter::Nearest, Reduction::Min);
+        assert_eq!(min.resize(&input).unwrap().buffer, vec![0, 0, 0, 200, 200, 200]);
+    }
+
+    #[test]
+    fn test_max_and_median() {
+        let input = Buffer2d::new(
+            Dimensions { height: 2, width: 4 },
+            vec![10, 20, 90, 90, 30, 250, 80, 70],
+        );
+        let to = Dimensions { height: 1, width: 2 };
+        let mut max = Scale::<u8>::with_reduction(&input.dimensions, &to, Filter::Nearest, Reduction::Max);
+        assert_eq!(max.resize(&input).unwrap().buffer, vec![250, 90]);
+        let mut median = Scale::<u8>::with_reduction(&input.dimensions, &to, Filter::Nearest, Reduction::Median);
+        assert_eq!(median.resize(&input).unwrap().buffer, vec![20, 80]);
+    }
+
+    #[test]
+    fn test_reduction_then_grow() {
+        // Rows shrink with the reduction, columns grow with the filter.
+        let input = Buffer2d::new(
+            Dimensions { height: 2, width: 2 },
+            vec![0, 100, 200, 40],
+        );
+        let to = Dimensions { height: 1, width: 4 };
+        let mut max = Scale::<u8>::with_reduction(&input.dimensions, &to, Filter::Bilinear, Reduction::Max);
+        let result = max.resize(&input).unwrap();
+        assert_eq!(result.dimensions, to);
+        assert_eq!(result.buffer, vec![200, 175, 125, 100]);
+    }
+
+    #[test]
+    fn test_u16_keeps_depth() {
+        let mut scale = Scale::<u16>::new(&Dimensions { height: 2, width: 2 },
+                                          &Dimensions { height: 1, width: 1 });
+        let result = scale.resize(
+            &Buffer2d::new(Dimensions { height: 2, width: 2 }, vec![1000, 1001, 60000, 60003])
+        ).unwrap();
+        assert_eq!(result.buffer, vec![30501]);
+    }
+
+    #[test]
+    fn test_f32() {
+        let mut scale = Scale::<f32>::with_filter(&Dimensions { height: 1, width: 2 },
+                                                  &Dimensions { height: 1, width: 4 },
+                                                  Filter::Bilinear);
+        let result = scale.resize(
+            &Buffer2d::new(Dimensions { height: 1, width: 2 }, vec![-1., 1.])
+        ).unwrap();
+        assert_eq!(result.buffer, vec![-1., -0.5, 0.5, 1.]);
+    }
+
+    #[test]
+    fn test_rgb_channels_are_independent() {
+        let mut scale = Scale::<Rgb>::new(&Dimensions { height: 1, width: 2 },
+                                          &Dimensions { height: 1, width: 1 });
+        let result = scale.resize(
+            &Buffer2d::new(
+                Dimensions { height: 1, width: 2 },
+                vec![Rgb::new(255, 0, 10), Rgb::new(0, 255, 20)],
+            )
+        ).unwrap();
+        assert_eq!(result.buffer, vec![Rgb::new(128, 128, 15)]);
+    }
+
+    #[test]
+    fn test_rgba_array_reduction() {
+        let mut scale = Scale::<[u8; 4]>::with_reduction(&Dimensions { height: 1, width: 3 },
+                                                         &Dimensions { height: 1, width: 1 },
+                                                         Filter::Nearest,
+                                                         Reduction::Max);
+        let result = scale.resize(
+            &Buffer2d::new(
+                Dimensions { height: 1, width: 3 },
+                vec![[1, 200, 3, 255], [100, 2, 3, 0], [4, 5, 90, 128]],
+            )
+        ).unwrap();
+        assert_eq!(result.buffer, vec![[100, 200, 90, 255]]);
+    }
+
+    #[test]
+    fn test_strided_view() {
+        // 3x4 image, rows padded to 6 and every pixel followed by a 0.
+        let buffer = [
+            10, 0, 20, 0, 30, 0, 40, 0, 255, 255, 255, 255,
+            50, 0, 60, 0, 70, 0, 80, 0, 255, 255, 255, 255,
+            90, 0, 100, 0, 110, 0, 120, 0,
+        ];
+        let dimensions = Dimensions { height: 3, width: 4 };
+        let view = ImageView::new(&buffer, dimensions, 12, 2).unwrap();
+        let to = Dimensions { height: 2, width: 2 };
+        let mut scale = Scale::<u8>::new(&dimensions, &to);
+        assert_eq!(scale.resize(view).unwrap(), scale.resize(&view.to_buffer_2d()).unwrap());
+
+        let mut same = Scale::<u8>::new(&dimensions, &dimensions);
+        assert_eq!(same.resize(view).unwrap(), view.to_buffer_2d());
+    }
+
+    #[test]
+    fn test_box_shrink_matches_general_path() {
+        let from = Dimensions { height: 12, width: 45 };
+        let buffer = (0..from.total() as u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8)
+            .collect::<Vec<u8>>();
+        let image = Buffer2d::new(from, buffer.clone());
+        let wide = Buffer2d::new(from, buffer.iter().map(|&n| n as u16).collect());
+        for to in [
+            Dimensions { height: 4, width: 15 },
+            Dimensions { height: 12, width: 9 },
+            Dimensions { height: 1, width: 1 },
+        ] {
+            let mut scale = Scale::<u8>::new(&from, &to);
+            assert!(scale.box_factors.is_some());
+            let expected = Scale::<u16>::new(&from, &to).resize(&wide).unwrap();
+            let result = scale.resize(&image).unwrap();
+            assert_eq!(result.dimensions, to);
+            let widened = result.buffer.iter().map(|&n| n as u16).collect::<Vec<u16>>();
+            assert_eq!(widened, expected.buffer);
+        }
+        // Strided views take the general path.
+        let view = ImageView::new(&buffer, Dimensions { height: 6, width: 15 }, 90, 3).unwrap();
+        let to = Dimensions { height: 3, width: 5 };
+        let expected = Scale::<u8>::new(&view.dimensions(), &to)
+            .resize(view.to_buffer_2d().view())
+            .unwrap();
+        assert_eq!(Scale::<u8>::new(&view.dimensions(), &to).resize(view).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_wrong_dimensions() {
+        let mut scale = Scale::<u8>::new(&Dimensions { height: 4, width: 4 },
+                                   &Dimensions { height: 2, width: 2 });
+        let image = Buffer2d::new(Dimensions { height: 4, width: 3 }, vec![0; 12]);
+        assert_eq!(
+            scale.resize(&image),
+            Err(Error::DimensionsMismatch {
+                expected: Dimensions { height: 4, width: 4 },
+                actual: image.dimensions,
+            }),
         );
+        let short = Buffer2d::new(Dimensions { height: 4, width: 4 }, vec![0; 12]);
+        assert_eq!(scale.resize(&short), Err(Error::BufferLength { expected: 16, actual: 12 }));
     }
-}
\ No newline at end of file
+}
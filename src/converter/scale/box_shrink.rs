@@ -0,0 +1,30 @@
+use crate::ImageView;
+use crate::converter::simd::add_row;
+use crate::parallel::for_each_row_with;
+
+/// Mean of every `factors.0` by `factors.1` block of `image`, rounded to
+/// nearest like the general path. Rows are summed with SIMD, `false` if `image`
+/// isn't made of contiguous rows or the sums could overflow.
+pub fn box_shrink(
+    image: ImageView<'_, u8>,
+    (factor_x, factor_y): (usize, usize),
+    sums: &mut Vec<Vec<u16>>,
+    output: &mut [u8],
+) -> bool {
+    let width = image.dimensions().width;
+    if image.packed_row(0).is_none() || factor_y > (u16::MAX / u8::MAX as u16) as usize {
+        return false;
+    }
+    let area = (factor_x * factor_y) as u32;
+    for_each_row_with(output, width / factor_x, sums, |sums, y, row| {
+        sums.clear();
+        sums.resize(width, 0);
+        for source_y in y * factor_y..(y + 1) * factor_y {
+            add_row(sums, image.packed_row(source_y).unwrap());
+        }
+        for (out, block) in row.iter_mut().zip(sums.chunks_exact(factor_x)) {
+            *out = ((block.iter().map(|&n| n as u32).sum::<u32>() + area / 2) / area) as u8;
+        }
+    });
+    true
+}
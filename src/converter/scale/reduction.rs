@@ -0,0 +1,115 @@
+use std::cmp::Ordering;
+use crate::converter::scale::sample::Channel;
+
+/// How the source elements under a destination element are combined on the
+/// axes that shrink.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Reduction {
+    /// Average, see [`Filter`](super::Filter) for how it's weighted.
+    #[default]
+    Mean,
+    Median,
+    /// Darkest element, keeps thin dark strokes.
+    Min,
+    /// Brightest element, keeps highlights.
+    Max,
+    /// Weighted average of the other reductions, weights are relative to
+    /// their sum.
+    Mix { mean: u8, median: u8, min: u8, max: u8 },
+}
+
+impl Reduction {
+    /// Combine `values`, which may get reordered. `weights` hold the area
+    /// each value covers, only the mean uses them.
+    pub fn reduce<C: Channel>(&self, values: &mut [C], weights: &[i64]) -> C {
+        match *self {
+            Reduction::Mean => Self::mean(values, weights),
+            Reduction::Median => Self::median(values),
+            Reduction::Min => Self::extreme(values, Ordering::Less),
+            Reduction::Max => Self::extreme(values, Ordering::Greater),
+            Reduction::Mix { mean, median, min, max } => {
+                let total = mean as i64 + median as i64 + min as i64 + max as i64;
+                if total == 0 {
+                    return Self::mean(values, weights);
+                }
+                let mut sum = C::Accumulator::default();
+                for (weight, reduction) in [
+                    (mean, Reduction::Mean),
+                    (median, Reduction::Median),
+                    (min, Reduction::Min),
+                    (max, Reduction::Max),
+                ] {
+                    if weight > 0 {
+                        sum += reduction.reduce(values, weights).widen() * C::weight(weight as i64);
+                    }
+                }
+                C::narrow(sum, total)
+            }
+        }
+    }
+
+    fn mean<C: Channel>(values: &[C], weights: &[i64]) -> C {
+        let total: i64 = weights.iter().sum();
+        if total == 0 {
+            return C::default();
+        }
+        let mut sum = C::Accumulator::default();
+        values.iter()
+            .zip(weights.iter())
+            .for_each(|(&n, &w)| sum += n.widen() * C::weight(w));
+        C::narrow(sum, total)
+    }
+
+    /// Lower median for an even number of values.
+    fn median<C: Channel>(values: &mut [C]) -> C {
+        if values.is_empty() {
+            return C::default();
+        }
+        let middle = (values.len() - 1) / 2;
+        *values.select_nth_unstable_by(middle, Self::compare).1
+    }
+
+    fn extreme<C: Channel>(values: &[C], wanted: Ordering) -> C {
+        values.iter()
+            .copied()
+            .reduce(|best, n| if Self::compare(&n, &best) == wanted { n } else { best })
+            .unwrap_or_default()
+    }
+
+    /// Total order for channels that are only partially ordered, NaNs
+    /// compare equal to everything.
+    fn compare<C: Channel>(a: &C, b: &C) -> Ordering {
+        a.partial_cmp(b).unwrap_or(Ordering::Equal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::converter::scale::reduction::Reduction;
+
+    #[test]
+    fn test_reductions() {
+        let values = [40_u8, 10, 250, 30];
+        let weights = [1, 1, 1, 1];
+        let reduce = |reduction: Reduction| reduction.reduce(&mut values.clone(), &weights);
+        assert_eq!(reduce(Reduction::Mean), 83);
+        assert_eq!(reduce(Reduction::Median), 30);
+        assert_eq!(reduce(Reduction::Min), 10);
+        assert_eq!(reduce(Reduction::Max), 250);
+    }
+
+    #[test]
+    fn test_weighted_mean() {
+        assert_eq!(Reduction::Mean.reduce(&mut [10_u8, 40], &[2, 1]), 20);
+        assert_eq!(Reduction::Mean.reduce(&mut [1_f32, 2.5], &[1, 1]), 1.75);
+    }
+
+    #[test]
+    fn test_mix() {
+        let mix = Reduction::Mix { mean: 1, median: 0, min: 3, max: 0 };
+        assert_eq!(mix.reduce(&mut [100_u8, 20, 60], &[1, 1, 1]), 30);
+        let none = Reduction::Mix { mean: 0, median: 0, min: 0, max: 0 };
+        assert_eq!(none.reduce(&mut [100_u16, 20, 60], &[1, 1, 1]), 60);
+    }
+}
@@ -0,0 +1,138 @@
+use std::ops::{AddAssign, Mul};
+use crate::ImageView;
+use crate::converter::scale::box_shrink::box_shrink;
+
+/// Numeric type of a single channel.
+pub trait Channel: Copy + Default + PartialOrd + Send + Sync {
+    /// Wide enough to hold weighted sums of the channel without overflow.
+    type Accumulator: Copy + Default + AddAssign + Mul<Output=Self::Accumulator> + Send + Sync;
+
+    fn widen(self) -> Self::Accumulator;
+
+    fn weight(weight: i64) -> Self::Accumulator;
+
+    /// Divide a weighted sum by the sum of its weights, rounded to nearest
+    /// and saturating to the range of the channel.
+    fn narrow(sum: Self::Accumulator, denominator: i64) -> Self;
+}
+
+/// Element `Scale` can resample, every channel is resampled on its own.
+pub trait Sample: Copy + Default + Send + Sync {
+    type Channel: Channel;
+    const CHANNELS: usize;
+
+    fn channel(&self, index: usize) -> Self::Channel;
+
+    fn set_channel(&mut self, index: usize, value: Self::Channel);
+
+    /// Fast path for downscaling by whole `factors` (horizontal, vertical)
+    /// with the mean, returns `false` and leaves `output` untouched if the
+    /// type has none. `sums` is scratch space, one per row band.
+    fn box_shrink(
+        _image: ImageView<'_, Self>,
+        _factors: (usize, usize),
+        _sums: &mut Vec<Vec<u16>>,
+        _output: &mut [Self],
+    ) -> bool {
+        false
+    }
+}
+
+macro_rules! integer_channel {
+    ($($t:ty),*) => {$(
+        impl Channel for $t {
+            type Accumulator = i64;
+
+            fn widen(self) -> i64 {
+                self as i64
+            }
+
+            fn weight(weight: i64) -> i64 {
+                weight
+            }
+
+            fn narrow(sum: i64, denominator: i64) -> Self {
+                (sum + denominator / 2).div_euclid(denominator).clamp(<$t>::MIN as i64, <$t>::MAX as i64) as $t
+            }
+        }
+    )*};
+}
+
+integer_channel!(u8, u16);
+
+impl Sample for u8 {
+    type Channel = u8;
+    const CHANNELS: usize = 1;
+
+    fn channel(&self, _: usize) -> u8 {
+        *self
+    }
+
+    fn set_channel(&mut self, _: usize, value: u8) {
+        *self = value;
+    }
+
+    fn box_shrink(
+        image: ImageView<'_, u8>,
+        factors: (usize, usize),
+        sums: &mut Vec<Vec<u16>>,
+        output: &mut [u8],
+    ) -> bool {
+        box_shrink(image, factors, sums, output)
+    }
+}
+
+impl Sample for u16 {
+    type Channel = u16;
+    const CHANNELS: usize = 1;
+
+    fn channel(&self, _: usize) -> u16 {
+        *self
+    }
+
+    fn set_channel(&mut self, _: usize, value: u16) {
+        *self = value;
+    }
+}
+
+impl Channel for f32 {
+    type Accumulator = f64;
+
+    fn widen(self) -> f64 {
+        self as f64
+    }
+
+    fn weight(weight: i64) -> f64 {
+        weight as f64
+    }
+
+    fn narrow(sum: f64, denominator: i64) -> Self {
+        (sum / denominator as f64) as f32
+    }
+}
+
+impl Sample for f32 {
+    type Channel = f32;
+    const CHANNELS: usize = 1;
+
+    fn channel(&self, _: usize) -> f32 {
+        *self
+    }
+
+    fn set_channel(&mut self, _: usize, value: f32) {
+        *self = value;
+    }
+}
+
+impl<C: Channel, const N: usize> Sample for [C; N] where [C; N]: Default {
+    type Channel = C;
+    const CHANNELS: usize = N;
+
+    fn channel(&self, index: usize) -> C {
+        self[index]
+    }
+
+    fn set_channel(&mut self, index: usize, value: C) {
+        self[index] = value;
+    }
+}
@@ -0,0 +1,328 @@
+/// Resampling kernel.
+///
+/// `Nearest`, `Bilinear` and `Bicubic` interpolate the axes that grow and
+/// average the covered area on the axes that shrink. The other filters
+/// convolve in both directions, with the kernel stretched by the shrink
+/// factor so downscaled output doesn't alias.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Filter {
+    /// Duplicate rows and columns.
+    #[default]
+    Nearest,
+    /// Linear interpolation between the two closest elements.
+    Bilinear,
+    /// Cubic convolution over the four closest elements, with `a = -0.75`.
+    Bicubic,
+    /// Windowed sinc over four elements.
+    Lanczos2,
+    /// Windowed sinc over six elements.
+    Lanczos3,
+    /// Mitchell-Netravali cubic, `B = C = 1 / 3`.
+    Mitchell,
+    /// Catmull-Rom spline, `B = 0, C = 1 / 2`.
+    CatmullRom,
+    /// Gaussian with a standard deviation of half an element.
+    Gaussian,
+}
+
+impl Filter {
+    /// Whether shrinking axes use the exact box filter instead of the kernel.
+    pub fn shrinks_by_area(&self) -> bool {
+        matches!(self, Filter::Nearest | Filter::Bilinear | Filter::Bicubic)
+    }
+
+    /// Half width of the kernel, in source elements.
+    fn support(&self) -> f64 {
+        match self {
+            Filter::Nearest => 0.5,
+            Filter::Bilinear => 1.,
+            Filter::Bicubic => 2.,
+            Filter::Lanczos2 => 2.,
+            Filter::Lanczos3 => 3.,
+            Filter::Mitchell => 2.,
+            Filter::CatmullRom => 2.,
+            Filter::Gaussian => 2.,
+        }
+    }
+
+    fn kernel(&self, x: f64) -> f64 {
+        let x = x.abs();
+        match self {
+            Filter::Nearest => if x <= 0.5 { 1. } else { 0. },
+            Filter::Bilinear => (1. - x).max(0.),
+            Filter::Bicubic => {
+                const A: f64 = -0.75;
+                if x < 1. {
+                    ((A + 2.) * x - (A + 3.)) * x * x + 1.
+                } else if x < 2. {
+                    ((A * x - 5. * A) * x + 8. * A) * x - 4. * A
+                } else {
+                    0.
+                }
+            }
+            Filter::Lanczos2 => Self::lanczos(x, 2.),
+            Filter::Lanczos3 => Self::lanczos(x, 3.),
+            Filter::Mitchell => Self::bc_spline(x, 1. / 3., 1. / 3.),
+            Filter::CatmullRom => Self::bc_spline(x, 0., 0.5),
+            Filter::Gaussian => {
+                const SIGMA: f64 = 0.5;
+                if x < 2. { (-x * x / (2. * SIGMA * SIGMA)).exp() } else { 0. }
+            }
+        }
+    }
+
+    fn sinc(x: f64) -> f64 {
+        if x == 0. {
+            1.
+        } else {
+            let x = x * std::f64::consts::PI;
+            x.sin() / x
+        }
+    }
+
+    fn lanczos(x: f64, lobes: f64) -> f64 {
+        if x < lobes { Self::sinc(x) * Self::sinc(x / lobes) } else { 0. }
+    }
+
+    /// Mitchell-Netravali family of cubics.
+    fn bc_spline(x: f64, b: f64, c: f64) -> f64 {
+        if x < 1. {
+            ((12. - 9. * b - 6. * c) * x * x * x
+                + (-18. + 12. * b + 6. * c) * x * x
+                + (6. - 2. * b)) / 6.
+        } else if x < 2. {
+            ((-b - 6. * c) * x * x * x
+                + (6. * b + 30. * c) * x * x
+                + (-12. * b - 48. * c) * x
+                + (8. * b + 24. * c)) / 6.
+        } else {
+            0.
+        }
+    }
+}
+
+/// Source elements contributing to one destination element along an axis.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Taps {
+    pub start: usize,
+    pub weights: Vec<i32>,
+}
+
+/// Integer taps of every destination element along an axis, computed once
+/// and reused for every frame. The weights of every destination element
+/// add up to `denominator`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AxisWeights {
+    pub taps: Vec<Taps>,
+    pub denominator: i64,
+}
+
+impl AxisWeights {
+    /// Fractional bits of the interpolation weights.
+    pub const PRECISION: u32 = 14;
+    pub const ONE: i32 = 1 << Self::PRECISION;
+
+    pub fn new(initial_n: usize, final_n: usize, filter: Filter) -> Self {
+        if initial_n == final_n {
+            return Self::fixed(
+                (0..final_n)
+                    .map(|start| Taps { start, weights: vec![Self::ONE] })
+                    .collect()
+            );
+        }
+        if filter == Filter::Nearest {
+            return Self::nearest(initial_n, final_n);
+        }
+        let scale = initial_n as f64 / final_n as f64;
+        // Stretch the kernel over the elements each destination element
+        // covers when shrinking.
+        let stretch = if filter.shrinks_by_area() { 1. } else { scale.max(1.) };
+        let support = filter.support() * stretch;
+        let taps = (0..final_n)
+            .map(|i| {
+                // Align pixel centers, not corners.
+                let center = (i as f64 + 0.5) * scale - 0.5;
+                let first = (center - support).floor() as isize + 1;
+                let last = (center + support).ceil() as isize - 1;
+                let start = first.clamp(0, initial_n as isize - 1) as usize;
+                let end = last.clamp(0, initial_n as isize - 1) as usize;
+                let mut weights = vec![0_f64; end - start + 1];
+                for j in first..=last {
+                    // Elements beyond the edges repeat the edge.
+                    let index = j.clamp(0, initial_n as isize - 1) as usize;
+                    weights[index - start] += filter.kernel((center - j as f64) / stretch);
+                }
+                Taps { start, weights: Self::quantize(&weights) }
+            })
+            .collect();
+        Self::fixed(taps)
+    }
+
+    /// Exact box filter for shrinking axes.
+    ///
+    /// In units of `1 / final_n` source elements, destination element `i`
+    /// covers `[i * initial_n, (i + 1) * initial_n)` and source element `j`
+    /// covers `[j * final_n, (j + 1) * final_n)`. Their overlap is the
+    /// weight, so partially covered border elements count partially.
+    pub fn area(initial_n: usize, final_n: usize) -> Self {
+        let taps = (0..final_n)
+            .map(|i| {
+                let begin = i * initial_n;
+                let end = (i + 1) * initial_n;
+                let start = begin / final_n;
+                let weights = (start..end.div_ceil(final_n))
+                    .map(|j| {
+                        let overlap = end.min((j + 1) * final_n) - begin.max(j * final_n);
+                        overlap as i32
+                    })
+                    .collect();
+                Taps { start, weights }
+            })
+            .collect();
+        Self { taps, denominator: initial_n as i64 }
+    }
+
+    fn fixed(taps: Vec<Taps>) -> Self {
+        Self { taps, denominator: Self::ONE as i64 }
+    }
+
+    /// Spread the duplicated elements evenly along the axis.
+    fn nearest(initial_n: usize, final_n: usize) -> Self {
+        let stride = final_n as f64 / (final_n - initial_n) as f64;
+        let mut duplicated = vec![false; final_n];
+        (1..=final_n - initial_n)
+            .map(|elem| (elem as f64 * stride).ceil() as usize - 1)
+            .for_each(|index| duplicated[index] = true);
+
+        let mut source = 0;
+        let taps = duplicated.iter()
+            .enumerate()
+            .map(|(index, &duplicated)| {
+                if !duplicated && index > 0 {
+                    source += 1;
+                }
+                Taps { start: source, weights: vec![Self::ONE] }
+            })
+            .collect();
+        Self::fixed(taps)
+    }
+
+    /// Normalize `weights` to add up to `ONE` exactly.
+    fn quantize(weights: &[f64]) -> Vec<i32> {
+        let sum: f64 = weights.iter().sum();
+        let mut quantized = weights.iter()
+            .map(|w| (w / sum * Self::ONE as f64).round() as i32)
+            .collect::<Vec<i32>>();
+        let error = Self::ONE - quantized.iter().sum::<i32>();
+        if let Some(largest) = quantized.iter_mut().max_by_key(|w| w.abs()) {
+            *largest += error;
+        }
+        quantized
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::converter::scale::weights::{AxisWeights, Filter, Taps};
+
+    const CONVOLUTIONS: [Filter; 5] = [
+        Filter::Lanczos2, Filter::Lanczos3, Filter::Mitchell, Filter::CatmullRom, Filter::Gaussian,
+    ];
+
+    #[test]
+    fn test_identity() {
+        let weights = AxisWeights::new(2, 2, Filter::Bicubic);
+        assert_eq!(weights.taps, vec![
+            Taps { start: 0, weights: vec![AxisWeights::ONE] },
+            Taps { start: 1, weights: vec![AxisWeights::ONE] },
+        ]);
+    }
+
+    #[test]
+    fn test_bilinear_double() {
+        let weights = AxisWeights::new(2, 4, Filter::Bilinear);
+        let one = AxisWeights::ONE;
+        assert_eq!(weights.taps, vec![
+            Taps { start: 0, weights: vec![one] },
+            Taps { start: 0, weights: vec![one * 3 / 4, one / 4] },
+            Taps { start: 0, weights: vec![one / 4, one * 3 / 4] },
+            Taps { start: 1, weights: vec![one] },
+        ]);
+    }
+
+    #[test]
+    fn test_nearest_spreads_duplicates() {
+        let weights = AxisWeights::new(5, 6, Filter::Nearest);
+        let sources = weights.taps.iter().map(|taps| taps.start).collect::<Vec<usize>>();
+        assert_eq!(sources, vec![0, 1, 2, 3, 4, 4]);
+        let weights = AxisWeights::new(2, 5, Filter::Nearest);
+        let sources = weights.taps.iter().map(|taps| taps.start).collect::<Vec<usize>>();
+        assert_eq!(sources, vec![0, 0, 1, 1, 1]);
+    }
+
+    #[test]
+    fn test_weights_add_up_to_one() {
+        for filter in [Filter::Nearest, Filter::Bilinear, Filter::Bicubic] {
+            let weights = AxisWeights::new(7, 23, filter);
+            assert_eq!(weights.taps.len(), 23);
+            for taps in weights.taps {
+                assert_eq!(taps.weights.iter().sum::<i32>(), AxisWeights::ONE);
+                assert!(taps.start + taps.weights.len() <= 7);
+            }
+        }
+        for filter in CONVOLUTIONS {
+            for (initial_n, final_n) in [(7, 23), (23, 7)] {
+                let weights = AxisWeights::new(initial_n, final_n, filter);
+                assert_eq!(weights.taps.len(), final_n);
+                for taps in weights.taps {
+                    assert_eq!(taps.weights.iter().sum::<i32>(), AxisWeights::ONE);
+                    assert!(taps.start + taps.weights.len() <= initial_n);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_kernels_interpolate() {
+        // Every kernel but the blurring ones goes through the samples.
+        for filter in [Filter::Lanczos2, Filter::Lanczos3, Filter::CatmullRom] {
+            assert_eq!(filter.kernel(0.), 1.);
+            assert!(filter.kernel(1.).abs() < 1e-9);
+            assert!(filter.kernel(2.).abs() < 1e-9);
+        }
+        assert!(Filter::Mitchell.kernel(1.) > 0.);
+        assert!(Filter::Gaussian.kernel(1.) > 0.);
+    }
+
+    #[test]
+    fn test_kernel_widens_when_shrinking() {
+        let grow = AxisWeights::new(10, 20, Filter::Lanczos3);
+        let shrink = AxisWeights::new(100, 25, Filter::Lanczos3);
+        let widest = |weights: &AxisWeights| {
+            weights.taps.iter().map(|taps| taps.weights.len()).max().unwrap()
+        };
+        assert!(widest(&grow) <= 6);
+        assert!(widest(&shrink) >= 4 * 6 - 1);
+    }
+
+    #[test]
+    fn test_area_integer_ratio() {
+        let weights = AxisWeights::area(6, 2);
+        assert_eq!(weights.denominator, 6);
+        assert_eq!(weights.taps, vec![
+            Taps { start: 0, weights: vec![2, 2, 2] },
+            Taps { start: 3, weights: vec![2, 2, 2] },
+        ]);
+    }
+
+    #[test]
+    fn test_area_fractional_ratio() {
+        let weights = AxisWeights::area(5, 2);
+        assert_eq!(weights.denominator, 5);
+        assert_eq!(weights.taps, vec![
+            Taps { start: 0, weights: vec![2, 2, 1] },
+            Taps { start: 2, weights: vec![1, 2, 2] },
+        ]);
+    }
+}
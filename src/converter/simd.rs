@@ -0,0 +1,210 @@
+//! Vectorized inner loops, picked at run time from what the CPU supports.
+//! Every function has a scalar version the vector ones must match exactly.
+
+use crate::converter::cpixel::Cpixel;
+
+/// Add every element of `row` to the element of `sums` at the same index.
+/// `sums` must be as long as `row` and not overflow.
+pub fn add_row(sums: &mut [u16], row: &[u8]) {
+    assert_eq!(sums.len(), row.len(), "Row and sums have different lengths.");
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            // Safe, the CPU supports AVX2.
+            return unsafe { add_row_avx2(sums, row) };
+        }
+        // SSE2 is part of x86-64.
+        unsafe { add_row_sse2(sums, row) }
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            // Safe, the CPU supports NEON.
+            return unsafe { add_row_neon(sums, row) };
+        }
+        add_row_scalar(sums, row)
+    }
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    add_row_scalar(sums, row)
+}
+
+/// Glyph of every brightness in `brightness`, see [`Cpixel::table`].
+pub fn map_glyphs(brightness: &[u8], output: &mut [Cpixel]) {
+    assert_eq!(brightness.len(), output.len(), "Input and output have different lengths.");
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            // Safe, the CPU supports AVX2.
+            return unsafe { map_glyphs_avx2(brightness, output) };
+        }
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            // Safe, the CPU supports NEON.
+            return unsafe { map_glyphs_neon(brightness, output) };
+        }
+    }
+    map_glyphs_scalar(brightness, output)
+}
+
+fn add_row_scalar(sums: &mut [u16], row: &[u8]) {
+    sums.iter_mut().zip(row.iter()).for_each(|(sum, &n)| *sum += n as u16);
+}
+
+fn map_glyphs_scalar(brightness: &[u8], output: &mut [Cpixel]) {
+    let table = Cpixel::table();
+    output.iter_mut().zip(brightness.iter()).for_each(|(glyph, &n)| *glyph = table[n as usize]);
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn add_row_sse2(sums: &mut [u16], row: &[u8]) {
+    use std::arch::x86_64::*;
+    const LANES: usize = 16;
+    let zero = _mm_setzero_si128();
+    let whole = row.len() / LANES * LANES;
+    for i in (0..whole).step_by(LANES) {
+        let bytes = _mm_loadu_si128(row.as_ptr().add(i) as *const __m128i);
+        let low = sums.as_mut_ptr().add(i) as *mut __m128i;
+        let high = sums.as_mut_ptr().add(i + LANES / 2) as *mut __m128i;
+        let low_bytes = _mm_unpacklo_epi8(bytes, zero);
+        let high_bytes = _mm_unpackhi_epi8(bytes, zero);
+        _mm_storeu_si128(low, _mm_add_epi16(_mm_loadu_si128(low), low_bytes));
+        _mm_storeu_si128(high, _mm_add_epi16(_mm_loadu_si128(high), high_bytes));
+    }
+    add_row_scalar(&mut sums[whole..], &row[whole..]);
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn add_row_avx2(sums: &mut [u16], row: &[u8]) {
+    use std::arch::x86_64::*;
+    const LANES: usize = 16;
+    let whole = row.len() / LANES * LANES;
+    for i in (0..whole).step_by(LANES) {
+        let bytes = _mm256_cvtepu8_epi16(_mm_loadu_si128(row.as_ptr().add(i) as *const __m128i));
+        let sum = sums.as_mut_ptr().add(i) as *mut __m256i;
+        _mm256_storeu_si256(sum, _mm256_add_epi16(_mm256_loadu_si256(sum), bytes));
+    }
+    add_row_scalar(&mut sums[whole..], &row[whole..]);
+}
+
+/// Gathers the glyphs of 8 elements at a time, `Cpixel` is a `char`.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn map_glyphs_avx2(brightness: &[u8], output: &mut [Cpixel]) {
+    use std::arch::x86_64::*;
+    const LANES: usize = 8;
+    let table = Cpixel::table().as_ptr() as *const i32;
+    let whole = brightness.len() / LANES * LANES;
+    for i in (0..whole).step_by(LANES) {
+        let indices = _mm256_cvtepu8_epi32(
+            _mm_loadl_epi64(brightness.as_ptr().add(i) as *const __m128i)
+        );
+        let glyphs = _mm256_i32gather_epi32::<4>(table, indices);
+        _mm256_storeu_si256(output.as_mut_ptr().add(i) as *mut __m256i, glyphs);
+    }
+    map_glyphs_scalar(&brightness[whole..], &mut output[whole..]);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn add_row_neon(sums: &mut [u16], row: &[u8]) {
+    use std::arch::aarch64::*;
+    const LANES: usize = 16;
+    let whole = row.len() / LANES * LANES;
+    for i in (0..whole).step_by(LANES) {
+        let bytes = vld1q_u8(row.as_ptr().add(i));
+        let low = sums.as_mut_ptr().add(i);
+        let high = sums.as_mut_ptr().add(i + LANES / 2);
+        vst1q_u16(low, vaddw_u8(vld1q_u16(low), vget_low_u8(bytes)));
+        vst1q_u16(high, vaddw_high_u8(vld1q_u16(high), bytes));
+    }
+    add_row_scalar(&mut sums[whole..], &row[whole..]);
+}
+
+/// Looks up 16 ASCII glyphs at a time in the 256 byte table, split in four
+/// 64 byte lookups, then widens them to `char`.
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn map_glyphs_neon(brightness: &[u8], output: &mut [Cpixel]) {
+    use std::arch::aarch64::*;
+    const LANES: usize = 16;
+    let ascii = Cpixel::ascii_table().as_ptr();
+    let tables = [
+        vld1q_u8_x4(ascii),
+        vld1q_u8_x4(ascii.add(64)),
+        vld1q_u8_x4(ascii.add(128)),
+        vld1q_u8_x4(ascii.add(192)),
+    ];
+    let whole = brightness.len() / LANES * LANES;
+    for i in (0..whole).step_by(LANES) {
+        let indices = vld1q_u8(brightness.as_ptr().add(i));
+        // Out of range indices leave the lanes untouched.
+        let mut bytes = vqtbl4q_u8(tables[0], indices);
+        bytes = vqtbx4q_u8(bytes, tables[1], vsubq_u8(indices, vdupq_n_u8(64)));
+        bytes = vqtbx4q_u8(bytes, tables[2], vsubq_u8(indices, vdupq_n_u8(128)));
+        bytes = vqtbx4q_u8(bytes, tables[3], vsubq_u8(indices, vdupq_n_u8(192)));
+        let out = output.as_mut_ptr().add(i) as *mut u32;
+        let low = vmovl_u8(vget_low_u8(bytes));
+        let high = vmovl_high_u8(bytes);
+        vst1q_u32(out, vmovl_u16(vget_low_u16(low)));
+        vst1q_u32(out.add(4), vmovl_high_u16(low));
+        vst1q_u32(out.add(8), vmovl_u16(vget_low_u16(high)));
+        vst1q_u32(out.add(12), vmovl_high_u16(high));
+    }
+    map_glyphs_scalar(&brightness[whole..], &mut output[whole..]);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::converter::cpixel::Cpixel;
+    use crate::converter::simd::{add_row, add_row_scalar, map_glyphs, map_glyphs_scalar};
+
+    fn bytes(len: usize, seed: u32) -> Vec<u8> {
+        (0..len as u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) ^ seed).rotate_left(9) as u8)
+            .collect()
+    }
+
+    #[test]
+    fn test_add_row_matches_scalar() {
+        // Lengths around the vector widths exercise the scalar tails.
+        for len in [0, 1, 7, 15, 16, 17, 31, 32, 33, 100] {
+            let mut expected = vec![0_u16; len];
+            let mut sums = vec![0_u16; len];
+            for seed in 0..5 {
+                let row = bytes(len, seed);
+                add_row_scalar(&mut expected, &row);
+                add_row(&mut sums, &row);
+            }
+            assert_eq!(sums, expected);
+        }
+    }
+
+    #[test]
+    fn test_map_glyphs_matches_scalar() {
+        let mut every = (0..=255).collect::<Vec<u8>>();
+        every.extend(bytes(37, 3));
+        for len in [0, 3, 8, 16, 17, every.len()] {
+            let mut expected = vec![Cpixel(' '); len];
+            let mut output = vec![Cpixel(' '); len];
+            map_glyphs_scalar(&every[..len], &mut expected);
+            map_glyphs(&every[..len], &mut output);
+            assert_eq!(output, expected);
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn test_sse2_matches_scalar() {
+        // Tested on its own, AVX2 machines never dispatch to it.
+        let row = bytes(45, 1);
+        let mut expected = vec![7_u16; row.len()];
+        let mut sums = expected.clone();
+        add_row_scalar(&mut expected, &row);
+        unsafe { super::add_row_sse2(&mut sums, &row) };
+        assert_eq!(sums, expected);
+    }
+}
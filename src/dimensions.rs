@@ -1,6 +1,8 @@
 use std::cmp::Ordering;
+use crate::error::{Error, Result};
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Dimensions {
     pub height: usize,
     pub width: usize,
@@ -10,11 +12,9 @@ impl Dimensions {
     pub fn fit_with_locked_ratio(
         image_dimensions: &Dimensions,
         screen_dimensions: &Dimensions,
-    ) -> Self {
-        assert_ne!(image_dimensions.width, 0, "Input image width can't be 0.");
-        assert_ne!(image_dimensions.height, 0, "Input image height can't be 0.");
-        assert_ne!(screen_dimensions.width, 0, "Screen width can't be 0.");
-        assert_ne!(screen_dimensions.height, 0, "Screen height can't be 0.");
+    ) -> Result<Self> {
+        image_dimensions.non_empty()?;
+        screen_dimensions.non_empty()?;
         let image_ratio = image_dimensions.ratio();
         let screen_ratio = screen_dimensions.ratio();
         let mut final_dim = match image_ratio.partial_cmp(&screen_ratio) {
@@ -36,14 +36,21 @@ impl Dimensions {
             Some(Ordering::Equal) => {
                 *screen_dimensions
             }
-            // Comparison failed.
-            None => {
-                panic!("Error comparing screen ratio with image ratio.")
-            }
+            // Both ratios are finite, dimensions aren't empty.
+            None => unreachable!(),
         };
-        if final_dim.height <= 0 { final_dim.height = 1 }
-        if final_dim.width <= 0 { final_dim.width = 1 }
-        final_dim
+        if final_dim.height == 0 { final_dim.height = 1 }
+        if final_dim.width == 0 { final_dim.width = 1 }
+        Ok(final_dim)
+    }
+
+    /// `EmptyDimensions` if there's no width or no height.
+    pub fn non_empty(&self) -> Result<()> {
+        if self.total() == 0 {
+            Err(Error::EmptyDimensions(*self))
+        } else {
+            Ok(())
+        }
     }
 
     pub fn ratio(&self) -> f64 {
@@ -54,11 +61,32 @@ impl Dimensions {
         self.height * self.width
     }
 
+    /// Output dimensions for an image of `image_dimensions` shown in
+    /// `constraints` with `mode`.
+    pub fn fit(
+        image_dimensions: &Dimensions,
+        constraints: &Dimensions,
+        mode: FitMode,
+    ) -> Result<Self> {
+        image_dimensions.non_empty()?;
+        constraints.non_empty()?;
+        Ok(match mode {
+            FitMode::Contain => Self::fit_with_locked_ratio(image_dimensions, constraints)?,
+            FitMode::Cover | FitMode::Stretch => *constraints,
+            FitMode::FixedWidth => {
+                image_dimensions.locked_ratio_resize(&Dim::Width(constraints.width))
+            }
+            FitMode::FixedHeight => {
+                image_dimensions.locked_ratio_resize(&Dim::Height(constraints.height))
+            }
+        })
+    }
+
     pub fn locked_ratio_resize(&self, dim: &Dim) -> Self {
         match *dim {
             Dim::Width(w) => Dimensions {
                 height: match (self.ratio() * w as f64) as usize {
-                    h if h == 0 => 1,
+                    0 => 1,
                     h => h,
                 },
                 width: w,
@@ -66,7 +94,7 @@ impl Dimensions {
             Dim::Height(h) => Dimensions {
                 height: h,
                 width: match (h as f64 / self.ratio()) as usize {
-                    w if w == 0 => 1,
+                    0 => 1,
                     w => w,
                 },
             },
@@ -92,7 +120,159 @@ impl PartialOrd for Dimensions {
     }
 }
 
-#[allow(dead_code)]
+/// How the image is sized to the output constraints.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FitMode {
+    /// Largest size with the image ratio inside the constraints.
+    #[default]
+    Contain,
+    /// Fill the constraints, cutting the edges of the image that don't fit
+    /// so its center stays visible.
+    Cover,
+    /// Fill the constraints, distorting the image.
+    Stretch,
+    /// Constraint width, height from the image ratio even if it's taller
+    /// than the constraints.
+    FixedWidth,
+    /// Constraint height, width from the image ratio even if it's wider
+    /// than the constraints.
+    FixedHeight,
+}
+
+/// Rectangle inside an image, `x` and `y` are its top left corner.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub height: usize,
+    pub width: usize,
+}
+
+impl Rect {
+    /// The whole of an image of `dimensions`.
+    pub fn full(dimensions: &Dimensions) -> Self {
+        Rect { x: 0, y: 0, height: dimensions.height, width: dimensions.width }
+    }
+
+    pub fn dimensions(&self) -> Dimensions {
+        Dimensions { height: self.height, width: self.width }
+    }
+
+    /// Whether the rectangle is non empty and inside `frame`.
+    pub fn is_inside(&self, frame: &Dimensions) -> bool {
+        self.height > 0 && self.width > 0
+            && self.x + self.width <= frame.width
+            && self.y + self.height <= frame.height
+    }
+
+    /// Move the rectangle inside `frame`, shrinking it only if it's larger.
+    pub fn fit_inside(&self, frame: &Dimensions) -> Self {
+        let width = self.width.clamp(1, frame.width.max(1));
+        let height = self.height.clamp(1, frame.height.max(1));
+        Rect {
+            x: self.x.min(frame.width.saturating_sub(width)),
+            y: self.y.min(frame.height.saturating_sub(height)),
+            height,
+            width,
+        }
+    }
+
+    /// Scale the rectangle by `1 / factor` around its center, so a factor
+    /// above 1 zooms in. The result stays inside `frame`.
+    pub fn zoomed(&self, factor: f64, frame: &Dimensions) -> Self {
+        let factor = if factor.is_finite() && factor > 0. { factor } else { 1. };
+        let width = ((self.width as f64 / factor).round() as usize).clamp(1, frame.width.max(1));
+        let height = ((self.height as f64 / factor).round() as usize).clamp(1, frame.height.max(1));
+        let center_x = self.x as f64 + self.width as f64 / 2.;
+        let center_y = self.y as f64 + self.height as f64 / 2.;
+        Rect {
+            x: (center_x - width as f64 / 2.).round().max(0.) as usize,
+            y: (center_y - height as f64 / 2.).round().max(0.) as usize,
+            height,
+            width,
+        }.fit_inside(frame)
+    }
+
+    /// Largest rectangle with `ratio` (height / width) centered inside this
+    /// one.
+    pub fn centered_with_ratio(&self, ratio: f64) -> Self {
+        let (height, width) = if self.dimensions().ratio() > ratio {
+            let height = (self.width as f64 * ratio).round() as usize;
+            (height.clamp(1, self.height.max(1)), self.width)
+        } else {
+            let width = (self.height as f64 / ratio).round() as usize;
+            (self.height, width.clamp(1, self.width.max(1)))
+        };
+        Rect {
+            x: self.x + (self.width - width) / 2,
+            y: self.y + (self.height - height) / 2,
+            height,
+            width,
+        }
+    }
+
+    /// Move the rectangle by `dx` and `dy`, stopping at the borders of
+    /// `frame`.
+    pub fn panned(&self, dx: isize, dy: isize, frame: &Dimensions) -> Self {
+        Rect {
+            x: self.x.saturating_add_signed(dx),
+            y: self.y.saturating_add_signed(dy),
+            ..*self
+        }.fit_inside(frame)
+    }
+}
+
+/// Clockwise rotation.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Rotation {
+    #[default]
+    None,
+    Quarter,
+    Half,
+    ThreeQuarters,
+}
+
+impl Rotation {
+    /// Rotation of `degrees` clockwise, `None` if it isn't a multiple of 90.
+    pub fn from_degrees(degrees: i32) -> Option<Self> {
+        match degrees.rem_euclid(360) {
+            0 => Some(Rotation::None),
+            90 => Some(Rotation::Quarter),
+            180 => Some(Rotation::Half),
+            270 => Some(Rotation::ThreeQuarters),
+            _ => None,
+        }
+    }
+}
+
+/// How to turn a frame upright: rotate it clockwise, then mirror the
+/// rotated frame.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Orientation {
+    pub rotation: Rotation,
+    /// Swap left and right, for front cameras.
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+}
+
+impl Orientation {
+    /// Dimensions of a frame of `dimensions` once oriented.
+    pub fn apply(&self, dimensions: &Dimensions) -> Dimensions {
+        if self.swaps_axes() {
+            Dimensions { height: dimensions.width, width: dimensions.height }
+        } else {
+            *dimensions
+        }
+    }
+
+    pub fn swaps_axes(&self) -> bool {
+        matches!(self.rotation, Rotation::Quarter | Rotation::ThreeQuarters)
+    }
+}
+
 #[derive(Copy, Clone)]
 pub enum Dim {
     Width(usize),
@@ -101,13 +281,15 @@ pub enum Dim {
 
 #[cfg(test)]
 mod tests {
-    use super::{Dimensions};
+    use super::{Dimensions, FitMode, Rect, Rotation, Orientation};
+    use crate::Error;
 
     #[test]
     fn test_closest_best_size_same_size() {
         let screen_dimensions = Dimensions { height: 300, width: 1 };
         let image_dimensions = Dimensions { height: 300, width: 1 };
-        let final_dim = Dimensions::fit_with_locked_ratio(&image_dimensions, &screen_dimensions);
+        let final_dim = Dimensions::fit_with_locked_ratio(&image_dimensions, &screen_dimensions)
+            .unwrap();
         assert_eq!(final_dim, image_dimensions);
     }
 
@@ -117,7 +299,7 @@ mod tests {
         let image_dimensions = Dimensions { height: 300, width: 1 };
         assert_eq!(screen_dimensions.ratio(), image_dimensions.ratio());
         let final_dim = Dimensions::fit_with_locked_ratio(&image_dimensions,
-                                                          &screen_dimensions);
+                                                          &screen_dimensions).unwrap();
         assert_eq!(final_dim, screen_dimensions);
     }
 
@@ -126,7 +308,7 @@ mod tests {
         let screen_dimensions = Dimensions { height: 100, width: 100 };
         let image_dimensions = Dimensions { height: 100, width: 200 };
         let final_dim = Dimensions::fit_with_locked_ratio(&image_dimensions,
-                                                          &screen_dimensions);
+                                                          &screen_dimensions).unwrap();
         assert_eq!(final_dim, Dimensions { height: 50, width: 100 });
     }
 
@@ -135,7 +317,7 @@ mod tests {
         let screen_dimensions = Dimensions { height: 100, width: 100 };
         let image_dimensions = Dimensions { height: 200, width: 100 };
         let final_dim = Dimensions::fit_with_locked_ratio(&image_dimensions,
-                                                          &screen_dimensions);
+                                                          &screen_dimensions).unwrap();
         assert_eq!(final_dim, Dimensions { height: 100, width: 50 });
     }
 
@@ -144,7 +326,7 @@ mod tests {
         let screen_dimensions = Dimensions { height: 100, width: 100 };
         let image_dimensions = Dimensions { height: 1, width: 200 };
         let final_dim = Dimensions::fit_with_locked_ratio(&image_dimensions,
-                                                          &screen_dimensions);
+                                                          &screen_dimensions).unwrap();
         assert_eq!(final_dim, Dimensions { height: 1, width: 100 });
     }
 
@@ -153,25 +335,96 @@ mod tests {
         let screen_dimensions = Dimensions { height: 100, width: 100 };
         let image_dimensions = Dimensions { height: 200, width: 1 };
         let final_dim = Dimensions::fit_with_locked_ratio(&image_dimensions,
-                                                          &screen_dimensions);
+                                                          &screen_dimensions).unwrap();
         assert_eq!(final_dim, Dimensions { height: 100, width: 1 });
     }
 
     #[test]
-    #[should_panic]
     fn test_cant_pass_image_with_zero() {
         let screen_dimensions = Dimensions { height: 10, width: 100 };
         let image_dimensions = Dimensions { height: 0, width: 1 };
-        Dimensions::fit_with_locked_ratio(&image_dimensions,
-                                          &screen_dimensions);
+        assert_eq!(
+            Dimensions::fit_with_locked_ratio(&image_dimensions, &screen_dimensions),
+            Err(Error::EmptyDimensions(image_dimensions)),
+        );
     }
 
     #[test]
-    #[should_panic]
     fn test_cant_pass_screen_with_zero() {
         let screen_dimensions = Dimensions { height: 10, width: 00 };
         let image_dimensions = Dimensions { height: 50, width: 1 };
-        Dimensions::fit_with_locked_ratio(&image_dimensions,
-                                          &screen_dimensions);
+        assert_eq!(
+            Dimensions::fit_with_locked_ratio(&image_dimensions, &screen_dimensions),
+            Err(Error::EmptyDimensions(screen_dimensions)),
+        );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_rect_fit_inside() {
+        let frame = Dimensions { height: 10, width: 20 };
+        let rect = Rect { x: 15, y: 8, height: 4, width: 10 };
+        assert_eq!(rect.fit_inside(&frame), Rect { x: 10, y: 6, height: 4, width: 10 });
+        let rect = Rect { x: 3, y: 3, height: 40, width: 10 };
+        assert_eq!(rect.fit_inside(&frame), Rect { x: 3, y: 0, height: 10, width: 10 });
+    }
+
+    #[test]
+    fn test_rect_zoom_keeps_center() {
+        let frame = Dimensions { height: 100, width: 200 };
+        let zoomed = Rect::full(&frame).zoomed(4., &frame);
+        assert_eq!(zoomed, Rect { x: 75, y: 38, height: 25, width: 50 });
+        assert_eq!(zoomed.zoomed(0.25, &frame), Rect::full(&frame));
+        assert_eq!(zoomed.zoomed(0.1, &frame), Rect::full(&frame));
+    }
+
+    #[test]
+    fn test_rect_zoom_near_border_stays_inside() {
+        let frame = Dimensions { height: 100, width: 100 };
+        let corner = Rect { x: 90, y: 0, height: 10, width: 10 };
+        let zoomed_out = corner.zoomed(0.5, &frame);
+        assert_eq!(zoomed_out, Rect { x: 80, y: 0, height: 20, width: 20 });
+        assert!(zoomed_out.is_inside(&frame));
+    }
+
+    #[test]
+    fn test_rect_pan_stops_at_border() {
+        let frame = Dimensions { height: 100, width: 100 };
+        let rect = Rect { x: 10, y: 10, height: 50, width: 50 };
+        assert_eq!(rect.panned(-20, 30, &frame), Rect { x: 0, y: 40, height: 50, width: 50 });
+        assert_eq!(rect.panned(100, 100, &frame), Rect { x: 50, y: 50, height: 50, width: 50 });
+    }
+
+    #[test]
+    fn test_orientation_dimensions() {
+        let dimensions = Dimensions { height: 3, width: 4 };
+        let quarter = Orientation { rotation: Rotation::Quarter, ..Default::default() };
+        assert_eq!(quarter.apply(&dimensions), Dimensions { height: 4, width: 3 });
+        let flipped = Orientation { flip_horizontal: true, ..Default::default() };
+        assert_eq!(flipped.apply(&dimensions), dimensions);
+        assert_eq!(Rotation::from_degrees(-90), Some(Rotation::ThreeQuarters));
+        assert_eq!(Rotation::from_degrees(45), None);
+    }
+
+    #[test]
+    fn test_fit_modes() {
+        let constraints = Dimensions { height: 100, width: 100 };
+        let image = Dimensions { height: 100, width: 200 };
+        let fit = |mode| Dimensions::fit(&image, &constraints, mode).unwrap();
+        assert_eq!(fit(FitMode::Contain), Dimensions { height: 50, width: 100 });
+        assert_eq!(fit(FitMode::Cover), constraints);
+        assert_eq!(fit(FitMode::Stretch), constraints);
+        assert_eq!(fit(FitMode::FixedWidth), Dimensions { height: 50, width: 100 });
+        assert_eq!(fit(FitMode::FixedHeight), Dimensions { height: 100, width: 200 });
+    }
+
+    #[test]
+    fn test_centered_with_ratio() {
+        let wide = Rect { x: 10, y: 0, height: 100, width: 200 };
+        assert_eq!(wide.centered_with_ratio(1.), Rect { x: 60, y: 0, height: 100, width: 100 });
+        let tall = Rect { x: 0, y: 0, height: 90, width: 30 };
+        assert_eq!(
+            tall.centered_with_ratio(0.5),
+            Rect { x: 0, y: 37, height: 15, width: 30 },
+        );
+    }
+}
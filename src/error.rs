@@ -0,0 +1,57 @@
+use std::fmt;
+use crate::dimensions::{Dimensions, Rect};
+
+/// Everything that can go wrong converting a frame.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    /// An image or output with no width or no height.
+    EmptyDimensions(Dimensions),
+    /// A frame doesn't have the dimensions it was expected to.
+    DimensionsMismatch { expected: Dimensions, actual: Dimensions },
+    /// A buffer doesn't hold as many elements as its dimensions need.
+    BufferLength { expected: usize, actual: usize },
+    /// Strides and offset address elements outside the buffer, or make
+    /// pixels overlap.
+    InvalidLayout,
+    /// A rectangle isn't inside the image.
+    OutOfBounds { rect: Rect, dimensions: Dimensions },
+    /// An aspect ratio that isn't finite and positive.
+    InvalidRatio(f64),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::EmptyDimensions(dimensions) => write!(
+                f, "Dimensions {}x{} are empty.", dimensions.width, dimensions.height,
+            ),
+            Error::DimensionsMismatch { expected, actual } => write!(
+                f,
+                "Expected a {}x{} image, got {}x{}.",
+                expected.width, expected.height, actual.width, actual.height,
+            ),
+            Error::BufferLength { expected, actual } => write!(
+                f, "Expected a buffer of {} elements, got {}.", expected, actual,
+            ),
+            Error::InvalidLayout => write!(f, "Image layout doesn't fit in its buffer."),
+            Error::OutOfBounds { rect, dimensions } => write!(
+                f,
+                "Rectangle {}x{} at ({}, {}) isn't inside a {}x{} image.",
+                rect.width, rect.height, rect.x, rect.y, dimensions.width, dimensions.height,
+            ),
+            Error::InvalidRatio(ratio) => write!(f, "Ratio {} isn't finite and positive.", ratio),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Lets conversions that can't fail, such as a view into itself, be used
+/// where a fallible one is expected.
+impl From<std::convert::Infallible> for Error {
+    fn from(error: std::convert::Infallible) -> Self {
+        match error {}
+    }
+}
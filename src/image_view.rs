@@ -0,0 +1,502 @@
+use std::convert::TryFrom;
+use std::ops::Index;
+use crate::dimensions::{Dimensions, Rect, Rotation, Orientation};
+use crate::buffer_2d::Buffer2d;
+use crate::error::{Error, Result};
+
+/// Borrowed image inside a larger buffer, such as a camera frame padded to
+/// a row stride wider than the image.
+///
+/// Element `(x, y)` is at `offset + y * row_stride + x * pixel_stride`,
+/// strides are in elements, not bytes.
+#[derive(Debug)]
+pub struct ImageView<'a, T> {
+    buffer: &'a [T],
+    dimensions: Dimensions,
+    offset: usize,
+    row_stride: isize,
+    pixel_stride: isize,
+}
+
+/// Mutable counterpart of [`ImageView`].
+#[derive(Debug)]
+pub struct ImageViewMut<'a, T> {
+    buffer: &'a mut [T],
+    dimensions: Dimensions,
+    offset: usize,
+    row_stride: isize,
+    pixel_stride: isize,
+}
+
+/// One row of an [`ImageView`].
+#[derive(Debug)]
+pub struct Row<'a, T> {
+    buffer: &'a [T],
+    start: isize,
+    stride: isize,
+    len: usize,
+}
+
+impl<'a, T> Clone for ImageView<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Copy for ImageView<'a, T> {}
+
+impl<'a, T> Clone for Row<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Copy for Row<'a, T> {}
+
+/// Whether the layout addresses only elements of a buffer of `len` and no
+/// two pixels share an element. Strides must also fit in an `isize`, so the
+/// views can negate them.
+fn fits(
+    len: usize,
+    dimensions: &Dimensions,
+    offset: usize,
+    row_stride: usize,
+    pixel_stride: usize,
+) -> bool {
+    if isize::try_from(row_stride).is_err() || isize::try_from(pixel_stride).is_err() {
+        return false;
+    }
+    if dimensions.total() == 0 {
+        return offset <= len;
+    }
+    let row_span = (dimensions.width - 1).checked_mul(pixel_stride);
+    let last = row_span.and_then(|row_span| {
+        (dimensions.height - 1)
+            .checked_mul(row_stride)?
+            .checked_add(row_span)?
+            .checked_add(offset)
+    });
+    match (row_span, last) {
+        (Some(row_span), Some(last)) => {
+            (dimensions.width == 1 || pixel_stride > 0)
+                && (dimensions.height == 1 || row_stride > row_span)
+                && last < len
+        }
+        _ => false,
+    }
+}
+
+impl<'a, T> ImageView<'a, T> {
+    /// View starting at the first element of `buffer`, `InvalidLayout` if
+    /// the layout doesn't fit in it.
+    pub fn new(
+        buffer: &'a [T],
+        dimensions: Dimensions,
+        row_stride: usize,
+        pixel_stride: usize,
+    ) -> Result<Self> {
+        Self::with_offset(buffer, dimensions, 0, row_stride, pixel_stride)
+    }
+
+    pub fn with_offset(
+        buffer: &'a [T],
+        dimensions: Dimensions,
+        offset: usize,
+        row_stride: usize,
+        pixel_stride: usize,
+    ) -> Result<Self> {
+        if fits(buffer.len(), &dimensions, offset, row_stride, pixel_stride) {
+            Ok(Self {
+                buffer,
+                dimensions,
+                offset,
+                row_stride: row_stride as isize,
+                pixel_stride: pixel_stride as isize,
+            })
+        } else {
+            Err(Error::InvalidLayout)
+        }
+    }
+
+    /// Tightly packed view, `BufferLength` if `buffer` is shorter than
+    /// `dimensions.total()`.
+    pub fn packed(buffer: &'a [T], dimensions: Dimensions) -> Result<Self> {
+        if buffer.len() < dimensions.total() {
+            return Err(Error::BufferLength { expected: dimensions.total(), actual: buffer.len() });
+        }
+        Self::new(buffer, dimensions, dimensions.width, 1)
+    }
+
+    pub fn dimensions(&self) -> Dimensions {
+        self.dimensions
+    }
+
+    pub fn row_stride(&self) -> isize {
+        self.row_stride
+    }
+
+    pub fn pixel_stride(&self) -> isize {
+        self.pixel_stride
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<&'a T> {
+        if x < self.dimensions.width && y < self.dimensions.height {
+            Some(&self.buffer[self.index(x, y)])
+        } else {
+            None
+        }
+    }
+
+    /// Row `y`, panics if it's outside the image.
+    pub fn row(&self, y: usize) -> Row<'a, T> {
+        assert!(y < self.dimensions.height, "Row {} is outside the image.", y);
+        Row {
+            buffer: self.buffer,
+            start: self.offset as isize + y as isize * self.row_stride,
+            stride: self.pixel_stride,
+            len: self.dimensions.width,
+        }
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item=Row<'a, T>> + 'a {
+        let view = *self;
+        (0..self.dimensions.height).map(move |y| view.row(y))
+    }
+
+    /// View of the part of the image inside `rect`, `OutOfBounds` if `rect`
+    /// isn't inside the image.
+    pub fn sub_view(&self, rect: &Rect) -> Result<ImageView<'a, T>> {
+        if !rect.is_inside(&self.dimensions) {
+            return Err(Error::OutOfBounds { rect: *rect, dimensions: self.dimensions });
+        }
+        Ok(ImageView {
+            offset: self.index(rect.x, rect.y),
+            dimensions: rect.dimensions(),
+            ..*self
+        })
+    }
+
+    /// The same pixels seen with `orientation` applied. Only the strides
+    /// change, nothing is copied.
+    pub fn oriented(&self, orientation: &Orientation) -> ImageView<'a, T> {
+        let view = match orientation.rotation {
+            Rotation::None => *self,
+            Rotation::Quarter => self.transposed().flipped_horizontally(),
+            Rotation::Half => self.flipped_horizontally().flipped_vertically(),
+            Rotation::ThreeQuarters => self.transposed().flipped_vertically(),
+        };
+        let view = if orientation.flip_horizontal { view.flipped_horizontally() } else { view };
+        if orientation.flip_vertical { view.flipped_vertically() } else { view }
+    }
+
+    fn transposed(&self) -> ImageView<'a, T> {
+        ImageView {
+            dimensions: Dimensions {
+                height: self.dimensions.width,
+                width: self.dimensions.height,
+            },
+            row_stride: self.pixel_stride,
+            pixel_stride: self.row_stride,
+            ..*self
+        }
+    }
+
+    fn flipped_horizontally(&self) -> ImageView<'a, T> {
+        if self.dimensions.total() == 0 {
+            return *self;
+        }
+        ImageView {
+            offset: self.index(self.dimensions.width - 1, 0),
+            pixel_stride: -self.pixel_stride,
+            ..*self
+        }
+    }
+
+    fn flipped_vertically(&self) -> ImageView<'a, T> {
+        if self.dimensions.total() == 0 {
+            return *self;
+        }
+        ImageView {
+            offset: self.index(0, self.dimensions.height - 1),
+            row_stride: -self.row_stride,
+            ..*self
+        }
+    }
+
+    /// Pixels in row major order.
+    pub fn iter(&self) -> impl Iterator<Item=&'a T> + 'a {
+        self.rows().flat_map(|row| row.iter())
+    }
+
+    /// The rows as slices, when pixels are contiguous.
+    pub fn packed_rows(&self) -> Option<impl Iterator<Item=&'a [T]> + 'a> {
+        if !self.is_packed() {
+            return None;
+        }
+        let view = *self;
+        Some((0..self.dimensions.height).map(move |y| view.packed_row(y).unwrap()))
+    }
+
+    /// Row `y` as a slice, `None` if pixels aren't contiguous or it's outside
+    /// the image.
+    pub fn packed_row(&self, y: usize) -> Option<&'a [T]> {
+        if !self.is_packed() || y >= self.dimensions.height {
+            return None;
+        }
+        let start = self.index(0, y);
+        Some(&self.buffer[start..start + self.dimensions.width])
+    }
+
+    fn is_packed(&self) -> bool {
+        self.pixel_stride == 1 || self.dimensions.width <= 1
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        (self.offset as isize + y as isize * self.row_stride + x as isize * self.pixel_stride)
+            as usize
+    }
+}
+
+impl<'a, T: Clone> ImageView<'a, T> {
+    /// Copy into a tightly packed buffer.
+    pub fn to_buffer_2d(&self) -> Buffer2d<T> {
+        Buffer2d::new(self.dimensions, self.iter().cloned().collect())
+    }
+}
+
+/// `BufferLength` if the buffer is shorter than its dimensions.
+impl<'a, T> TryFrom<&'a Buffer2d<T>> for ImageView<'a, T> {
+    type Error = Error;
+
+    fn try_from(image: &'a Buffer2d<T>) -> Result<Self> {
+        ImageView::packed(&image.buffer, image.dimensions)
+    }
+}
+
+impl<'a, T> Row<'a, T> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, x: usize) -> Option<&'a T> {
+        if x < self.len {
+            Some(&self.buffer[(self.start + x as isize * self.stride) as usize])
+        } else {
+            None
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=&'a T> + 'a {
+        let row = *self;
+        (0..self.len).map(move |x| &row.buffer[(row.start + x as isize * row.stride) as usize])
+    }
+}
+
+impl<'a, T> Index<usize> for Row<'a, T> {
+    type Output = T;
+
+    fn index(&self, x: usize) -> &T {
+        self.get(x).expect("Column is outside the row.")
+    }
+}
+
+impl<'a, T> ImageViewMut<'a, T> {
+    /// Mutable view starting at the first element of `buffer`,
+    /// `InvalidLayout` if the layout doesn't fit in it.
+    pub fn new(
+        buffer: &'a mut [T],
+        dimensions: Dimensions,
+        row_stride: usize,
+        pixel_stride: usize,
+    ) -> Result<Self> {
+        Self::with_offset(buffer, dimensions, 0, row_stride, pixel_stride)
+    }
+
+    pub fn with_offset(
+        buffer: &'a mut [T],
+        dimensions: Dimensions,
+        offset: usize,
+        row_stride: usize,
+        pixel_stride: usize,
+    ) -> Result<Self> {
+        if fits(buffer.len(), &dimensions, offset, row_stride, pixel_stride) {
+            Ok(Self {
+                buffer,
+                dimensions,
+                offset,
+                row_stride: row_stride as isize,
+                pixel_stride: pixel_stride as isize,
+            })
+        } else {
+            Err(Error::InvalidLayout)
+        }
+    }
+
+    pub fn dimensions(&self) -> Dimensions {
+        self.dimensions
+    }
+
+    pub fn as_view(&self) -> ImageView<'_, T> {
+        ImageView {
+            buffer: self.buffer,
+            dimensions: self.dimensions,
+            offset: self.offset,
+            row_stride: self.row_stride,
+            pixel_stride: self.pixel_stride,
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<&T> {
+        self.as_view().get(x, y)
+    }
+
+    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut T> {
+        if x < self.dimensions.width && y < self.dimensions.height {
+            let index = self.as_view().index(x, y);
+            Some(&mut self.buffer[index])
+        } else {
+            None
+        }
+    }
+
+    /// Call `f` with every pixel and its coordinates, in row major order.
+    pub fn for_each_mut(&mut self, mut f: impl FnMut(usize, usize, &mut T)) {
+        for y in 0..self.dimensions.height {
+            for x in 0..self.dimensions.width {
+                let index = self.as_view().index(x, y);
+                f(x, y, &mut self.buffer[index]);
+            }
+        }
+    }
+}
+
+impl<'a, T: Clone> ImageViewMut<'a, T> {
+    /// Copy the pixels of `source`, which must have the same dimensions.
+    pub fn copy_from(&mut self, source: &ImageView<'_, T>) {
+        assert_eq!(self.dimensions, source.dimensions, "Views have different dimensions.");
+        self.for_each_mut(|x, y, pixel| *pixel = source.row(y)[x].clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+    use crate::image_view::{ImageView, ImageViewMut};
+    use crate::{Dimensions, Rect, Rotation, Orientation, Buffer2d, Error};
+
+    #[test]
+    fn test_padded_rows() {
+        // 2x3 image with a row stride of 4, padding is 99.
+        let buffer = [1, 2, 3, 99, 4, 5, 6, 99];
+        let view = ImageView::new(&buffer, Dimensions { height: 2, width: 3 }, 4, 1).unwrap();
+        assert_eq!(view.iter().copied().collect::<Vec<i32>>(), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(view.get(2, 1), Some(&6));
+        assert_eq!(view.get(3, 1), None);
+        assert_eq!(
+            view.packed_rows().unwrap().collect::<Vec<&[i32]>>(),
+            vec![&[1, 2, 3][..], &[4, 5, 6][..]]
+        );
+    }
+
+    #[test]
+    fn test_pixel_stride() {
+        // Interleaved chroma, only the first of every pair belongs to the view.
+        let buffer = [10, 0, 11, 0, 12, 0, 13, 0];
+        let view = ImageView::new(&buffer, Dimensions { height: 2, width: 2 }, 4, 2).unwrap();
+        assert_eq!(view.to_buffer_2d().buffer, vec![10, 11, 12, 13]);
+        assert!(view.packed_rows().is_none());
+    }
+
+    #[test]
+    fn test_layout_must_fit() {
+        let buffer = [0; 7];
+        let dimensions = Dimensions { height: 2, width: 3 };
+        assert!(ImageView::new(&buffer, dimensions, 4, 1).is_ok());
+        assert!(ImageView::new(&buffer, dimensions, 5, 1).is_err());
+        assert!(ImageView::with_offset(&buffer, dimensions, 1, 4, 1).is_err());
+        // Rows would overlap.
+        assert!(ImageView::new(&buffer, dimensions, 2, 1).is_err());
+        assert!(ImageView::packed(&buffer, dimensions).is_ok());
+        // Strides so large the layout overflows.
+        let buffer = [0; 16];
+        let dimensions = Dimensions { height: 2, width: 2 };
+        assert_eq!(
+            ImageView::new(&buffer, dimensions, usize::MAX, 1).err(),
+            Some(Error::InvalidLayout)
+        );
+        assert!(ImageView::new(&buffer, dimensions, 4, usize::MAX).is_err());
+        assert!(ImageView::with_offset(&buffer, dimensions, usize::MAX, 4, 1).is_err());
+        assert!(ImageView::new(&buffer, Dimensions { height: 1, width: 1 }, usize::MAX, 1).is_err());
+    }
+
+    #[test]
+    fn test_sub_view() {
+        let image = Buffer2d::new(Dimensions { height: 3, width: 3 }, (1..=9).collect());
+        let rect = Rect { x: 1, y: 1, height: 2, width: 2 };
+        let view = image.view().sub_view(&rect).unwrap();
+        assert_eq!(view.to_buffer_2d().buffer, vec![5, 6, 8, 9]);
+        let rect = Rect { x: 0, y: 1, height: 1, width: 1 };
+        assert_eq!(view.sub_view(&rect).unwrap().to_buffer_2d().buffer, vec![8]);
+        let rect = Rect { x: 2, y: 1, height: 2, width: 2 };
+        assert!(image.view().sub_view(&rect).is_err());
+    }
+
+    #[test]
+    fn test_oriented() {
+        // 1 2 3
+        // 4 5 6
+        let image = Buffer2d::new(Dimensions { height: 2, width: 3 }, (1..=6).collect());
+        let oriented = |rotation, flip_horizontal, flip_vertical| {
+            let orientation = Orientation { rotation, flip_horizontal, flip_vertical };
+            let view = image.view().oriented(&orientation);
+            assert_eq!(view.dimensions(), orientation.apply(&image.dimensions));
+            view.to_buffer_2d().buffer
+        };
+        assert_eq!(oriented(Rotation::None, false, false), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(oriented(Rotation::Quarter, false, false), vec![4, 1, 5, 2, 6, 3]);
+        assert_eq!(oriented(Rotation::Half, false, false), vec![6, 5, 4, 3, 2, 1]);
+        assert_eq!(oriented(Rotation::ThreeQuarters, false, false), vec![3, 6, 2, 5, 1, 4]);
+        assert_eq!(oriented(Rotation::None, true, false), vec![3, 2, 1, 6, 5, 4]);
+        assert_eq!(oriented(Rotation::None, false, true), vec![4, 5, 6, 1, 2, 3]);
+        assert_eq!(oriented(Rotation::Quarter, true, false), vec![1, 4, 2, 5, 3, 6]);
+    }
+
+    #[test]
+    fn test_oriented_sub_view() {
+        let image = Buffer2d::new(Dimensions { height: 2, width: 3 }, (1..=6).collect());
+        let orientation = Orientation { rotation: Rotation::Quarter, ..Default::default() };
+        let view = image.view().oriented(&orientation);
+        let rect = Rect { x: 0, y: 1, height: 2, width: 2 };
+        assert_eq!(view.sub_view(&rect).unwrap().to_buffer_2d().buffer, vec![5, 2, 6, 3]);
+    }
+
+    #[test]
+    fn test_view_of_buffer_2d() {
+        let image = Buffer2d::new(Dimensions { height: 2, width: 2 }, vec![1, 2, 3, 4]);
+        let view = ImageView::try_from(&image).unwrap();
+        assert_eq!(view.to_buffer_2d(), image);
+        let short = Buffer2d::new(Dimensions { height: 2, width: 2 }, vec![1, 2, 3]);
+        assert_eq!(
+            ImageView::try_from(&short).err(),
+            Some(Error::BufferLength { expected: 4, actual: 3 })
+        );
+    }
+
+    #[test]
+    fn test_mut_view() {
+        let mut buffer = [0; 8];
+        {
+            let mut view = ImageViewMut::with_offset(
+                &mut buffer, Dimensions { height: 2, width: 2 }, 1, 4, 2,
+            ).unwrap();
+            *view.get_mut(1, 1).unwrap() = 7;
+            let source = [1, 2, 3, 7];
+            view.copy_from(&ImageView::packed(&source, view.dimensions()).unwrap());
+        }
+        assert_eq!(buffer, [0, 1, 0, 2, 0, 3, 0, 7]);
+    }
+}
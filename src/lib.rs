@@ -6,9 +6,26 @@ mod dimensions;
 mod pixel;
 mod buffer_2d;
 mod yuv;
+mod image_view;
+mod parallel;
+mod error;
 
-pub use converter::Converter;
+pub use converter::{
+    Converter, Cpixel, Scale, Filter, Reduction, Sample, Channel, OutputMode, Binarize, Threshold,
+    TemporalDenoise, ConverterConfig,
+};
 
-pub use dimensions::Dimensions;
+pub use error::{Error, Result};
 
-pub use buffer_2d::Buffer2d;
\ No newline at end of file
+pub use dimensions::{Dimensions, FitMode, Rect, Rotation, Orientation};
+
+pub use buffer_2d::Buffer2d;
+
+pub use image_view::{ImageView, ImageViewMut, Row};
+
+pub use pixel::{Rgb, Rgba};
+
+pub use yuv::{
+    Yuv420, YuvSemiPlanar, ChromaOrder, Yuv420Planes, Plane, Yuv422, Yuv422Order, YuvMatrix,
+    ColorRange,
+};
\ No newline at end of file
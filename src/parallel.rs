@@ -0,0 +1,130 @@
+//! Row loops shared by the conversion stages. With the `parallel` feature
+//! the rows are split in one band per thread of the rayon pool, every row
+//! is computed the same way in both cases so results are identical.
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Number of bands the rows are split in.
+fn band_count() -> usize {
+    #[cfg(feature = "parallel")]
+    let bands = rayon::current_num_threads();
+    #[cfg(not(feature = "parallel"))]
+    let bands = 1;
+    bands.max(1)
+}
+
+/// Rows in every band but the last, for `rows` rows.
+fn band_rows(rows: usize) -> usize {
+    rows.div_ceil(band_count()).max(1)
+}
+
+/// Call `f` with the index and contents of every `len` long row of
+/// `buffer`.
+pub fn for_each_row<T, F>(buffer: &mut [T], len: usize, f: F)
+    where T: Send, F: Fn(usize, &mut [T]) + Send + Sync
+{
+    if len == 0 {
+        return;
+    }
+    let band_rows = band_rows(buffer.len() / len);
+    let band = |(index, band): (usize, &mut [T])| {
+        for (y, row) in band.chunks_exact_mut(len).enumerate() {
+            f(index * band_rows + y, row);
+        }
+    };
+    #[cfg(feature = "parallel")]
+    buffer.par_chunks_mut(band_rows * len).enumerate().for_each(band);
+    #[cfg(not(feature = "parallel"))]
+    buffer.chunks_mut(band_rows * len).enumerate().for_each(band);
+}
+
+/// Like `for_each_row`, with scratch space for `f`. Every band works on its
+/// own element of `scratch`, which is grown to the number of bands and kept
+/// by the caller so it's allocated once.
+pub fn for_each_row_with<T, S, F>(buffer: &mut [T], len: usize, scratch: &mut Vec<S>, f: F)
+    where T: Send, S: Default + Send, F: Fn(&mut S, usize, &mut [T]) + Send + Sync
+{
+    if len == 0 {
+        return;
+    }
+    let band_rows = band_rows(buffer.len() / len);
+    let bands = buffer.len().div_ceil(band_rows * len);
+    if scratch.len() < bands {
+        scratch.resize_with(bands, S::default);
+    }
+    let band = |(index, (band, scratch)): (usize, (&mut [T], &mut S))| {
+        for (y, row) in band.chunks_exact_mut(len).enumerate() {
+            f(scratch, index * band_rows + y, row);
+        }
+    };
+    #[cfg(feature = "parallel")]
+    buffer.par_chunks_mut(band_rows * len)
+        .zip(scratch.par_iter_mut())
+        .enumerate()
+        .for_each(band);
+    #[cfg(not(feature = "parallel"))]
+    buffer.chunks_mut(band_rows * len).zip(scratch.iter_mut()).enumerate().for_each(band);
+}
+
+/// Call `f` with every pair of elements of `a` and `b`, which are made of
+/// `len` long rows.
+pub fn for_each_zip<A, B, F>(a: &mut [A], b: &mut [B], len: usize, f: F)
+    where A: Send, B: Send, F: Fn(&mut A, &mut B) + Send + Sync
+{
+    if len == 0 {
+        return;
+    }
+    let band_len = band_rows(a.len() / len) * len;
+    let band = |(a, b): (&mut [A], &mut [B])| {
+        a.iter_mut().zip(b.iter_mut()).for_each(|(a, b)| f(a, b));
+    };
+    #[cfg(feature = "parallel")]
+    a.par_chunks_mut(band_len).zip(b.par_chunks_mut(band_len)).for_each(band);
+    #[cfg(not(feature = "parallel"))]
+    a.chunks_mut(band_len).zip(b.chunks_mut(band_len)).for_each(band);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parallel::{for_each_row, for_each_row_with, for_each_zip, band_count};
+
+    #[test]
+    fn test_rows_get_their_index() {
+        let mut buffer = vec![0; 12];
+        for_each_row(&mut buffer, 4, |y, row| row.iter_mut().for_each(|n| *n = y));
+        assert_eq!(buffer, vec![0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2]);
+        for_each_row(&mut buffer, 0, |_, _| unreachable!());
+        // More bands than rows.
+        let mut buffer = vec![0; 3];
+        for_each_row(&mut buffer, 1, |y, row| row[0] = y);
+        assert_eq!(buffer, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_scratch_and_zip() {
+        let mut buffer = vec![0; 6];
+        let mut scratch = Vec::new();
+        for_each_row_with(&mut buffer, 2, &mut scratch, |scratch: &mut Vec<usize>, y, row| {
+            scratch.clear();
+            scratch.extend([y, y * 10]);
+            row.copy_from_slice(scratch);
+        });
+        assert_eq!(buffer, vec![0, 0, 1, 10, 2, 20]);
+        assert!(!scratch.is_empty() && scratch.len() <= band_count());
+        let mut other = vec![1; 6];
+        for_each_zip(&mut buffer, &mut other, 2, |a, b| *b += *a);
+        assert_eq!(other, vec![1, 1, 2, 11, 3, 21]);
+    }
+
+    #[test]
+    fn test_scratch_is_kept() {
+        let mut buffer = vec![0; 64];
+        let mut scratch = Vec::new();
+        let fill = |scratch: &mut Vec<u8>, _: usize, _: &mut [u8]| scratch.resize(16, 0);
+        for_each_row_with(&mut buffer, 4, &mut scratch, fill);
+        let pointers = scratch.iter().map(|s| s.as_ptr()).collect::<Vec<_>>();
+        for_each_row_with(&mut buffer, 4, &mut scratch, fill);
+        assert_eq!(scratch.iter().map(|s| s.as_ptr()).collect::<Vec<_>>(), pointers);
+    }
+}
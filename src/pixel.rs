@@ -1,22 +1,76 @@
 use std::ops::{Deref, DerefMut};
+use crate::converter::Sample;
 
 pub trait Pixel {
     fn into_desaturated(self) -> Brightness;
 }
 
-pub struct RGB {
+#[derive(Copy, Clone, Default, Eq, PartialEq, Debug)]
+pub struct Rgb {
     red: u8,
     green: u8,
     blue: u8,
 }
 
-impl Pixel for RGB {
+impl Rgb {
+    pub fn new(red: u8, green: u8, blue: u8) -> Self {
+        Rgb { red, green, blue }
+    }
+}
+
+impl Pixel for Rgb {
     fn into_desaturated(self) -> Brightness {
         (((self.red as u16 + self.green as u16 + self.blue as u16) / 3) as u8)
             .into()
     }
 }
 
+impl Sample for Rgb {
+    type Channel = u8;
+    const CHANNELS: usize = 3;
+
+    fn channel(&self, index: usize) -> u8 {
+        [self.red, self.green, self.blue][index]
+    }
+
+    fn set_channel(&mut self, index: usize, value: u8) {
+        *[&mut self.red, &mut self.green, &mut self.blue][index] = value;
+    }
+}
+
+#[derive(Copy, Clone, Default, Eq, PartialEq, Debug)]
+pub struct Rgba {
+    red: u8,
+    green: u8,
+    blue: u8,
+    alpha: u8,
+}
+
+impl Rgba {
+    pub fn new(red: u8, green: u8, blue: u8, alpha: u8) -> Self {
+        Rgba { red, green, blue, alpha }
+    }
+}
+
+impl Pixel for Rgba {
+    fn into_desaturated(self) -> Brightness {
+        Rgb::new(self.red, self.green, self.blue).into_desaturated()
+    }
+}
+
+impl Sample for Rgba {
+    type Channel = u8;
+    const CHANNELS: usize = 4;
+
+    fn channel(&self, index: usize) -> u8 {
+        [self.red, self.green, self.blue, self.alpha][index]
+    }
+
+    fn set_channel(&mut self, index: usize, value: u8) {
+        *[&mut self.red, &mut self.green, &mut self.blue, &mut self.alpha][index] = value;
+    }
+}
+
 impl Pixel for Brightness {
     fn into_desaturated(self) -> Brightness {
         self
@@ -24,9 +78,23 @@ impl Pixel for Brightness {
 }
 
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Default, Eq, PartialEq, Debug)]
 pub struct Brightness(u8);
 
+impl Sample for Brightness {
+    type Channel = u8;
+    const CHANNELS: usize = 1;
+
+    fn channel(&self, _: usize) -> u8 {
+        self.0
+    }
+
+    fn set_channel(&mut self, _: usize, value: u8) {
+        self.0 = value;
+    }
+}
+
+#[allow(dead_code)]
 impl Brightness {
     fn min() -> Self {
         u8::MIN.into()
@@ -40,7 +108,7 @@ impl Brightness {
         ((*self.deref() as u16 + *rhs.deref() as u16 / 2) as u8).into()
     }
 
-    fn to_byte(&self) -> u8 {
+    fn to_byte(self) -> u8 {
         *self.deref()
     }
 }
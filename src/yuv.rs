@@ -1,6 +1,732 @@
 use crate::dimensions::Dimensions;
+use crate::image_view::ImageView;
+use crate::buffer_2d::Buffer2d;
+use crate::pixel::Rgb;
+use crate::parallel::for_each_row;
+use crate::error::{Error, Result};
 
-pub struct Yuv420 {
+/// Planar YUV 4:2:0 frame (I420) read in place: a full resolution Y plane
+/// followed by U and V planes at half the resolution on both axes, rounded
+/// up.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Yuv420<'a> {
     pub dimensions: Dimensions,
-    pub buffer: Vec<u8>,
+    pub y: &'a [u8],
+    pub u: &'a [u8],
+    pub v: &'a [u8],
+    /// Width over height of a pixel, overrides the one of the converter
+    /// when set.
+    pub sample_aspect_ratio: Option<f64>,
+    pub range: ColorRange,
+}
+
+/// Semi-planar YUV 4:2:0 frame read in place: a full resolution Y plane
+/// followed by one plane of interleaved chroma pairs, at half the
+/// resolution on both axes.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct YuvSemiPlanar<'a> {
+    pub dimensions: Dimensions,
+    pub y: &'a [u8],
+    pub uv: &'a [u8],
+    pub order: ChromaOrder,
+    /// Width over height of a pixel, overrides the one of the converter
+    /// when set.
+    pub sample_aspect_ratio: Option<f64>,
+    pub range: ColorRange,
+}
+
+/// Which chroma sample comes first in every pair of a [`YuvSemiPlanar`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ChromaOrder {
+    /// NV12, emitted by most decoders.
+    Uv,
+    /// NV21, the default of the legacy Android camera API.
+    Vu,
+}
+
+/// YUV 4:2:0 frame read in place from three planes of any layout, like
+/// the `YUV_420_888` images of Android's Camera2 API. The chroma planes
+/// have half the resolution of the Y plane on both axes, rounded up.
+#[derive(Copy, Clone, Debug)]
+pub struct Yuv420Planes<'a> {
+    pub dimensions: Dimensions,
+    pub y: Plane<'a>,
+    pub u: Plane<'a>,
+    pub v: Plane<'a>,
+    /// Width over height of a pixel, overrides the one of the converter
+    /// when set.
+    pub sample_aspect_ratio: Option<f64>,
+    pub range: ColorRange,
+}
+
+/// Buffer and strides of one plane, strides are in bytes. The buffer
+/// starts at the first sample and can end right after the last one.
+#[derive(Copy, Clone, Debug)]
+pub struct Plane<'a> {
+    pub buffer: &'a [u8],
+    pub row_stride: usize,
+    pub pixel_stride: usize,
+}
+
+/// Packed YUV 4:2:2 frame, as captured by most USB webcams, read in place.
+/// Every two pixels share one chroma pair and take four bytes, in the
+/// order given by `order`.
+#[derive(Copy, Clone, Debug)]
+pub struct Yuv422<'a> {
+    pub dimensions: Dimensions,
+    pub buffer: &'a [u8],
+    /// Bytes from the start of a row to the next, at least twice the width
+    /// rounded up to whole pairs.
+    pub row_stride: usize,
+    pub order: Yuv422Order,
+    /// Width over height of a pixel, overrides the one of the converter
+    /// when set.
+    pub sample_aspect_ratio: Option<f64>,
+    pub range: ColorRange,
+}
+
+/// Byte order of every pair of pixels of a [`Yuv422`] frame.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Yuv422Order {
+    /// Y0 U Y1 V, also called YUY2.
+    Yuyv,
+    /// U Y0 V Y1.
+    Uyvy,
+}
+
+/// Values the samples of a frame span. Every input type starts with the
+/// range its format usually has, change it when the source says otherwise.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColorRange {
+    /// Studio swing of video, luma in 16..=235 and chroma in 16..=240.
+    Limited,
+    /// Every byte value, like JPEG and most camera previews.
+    Full,
+}
+
+lazy_static::lazy_static! {
+    /// Full range value of every limited range luma.
+    static ref LIMITED_LUMA: [u8; 256] = {
+        let mut luma = [0; 256];
+        for (n, full) in luma.iter_mut().enumerate() {
+            *full = ((n.clamp(16, 235) - 16) * 255 + 219 / 2) / 219;
+        }
+        luma.map(|n| n as u8)
+    };
+}
+
+impl ColorRange {
+    /// Stretch luma of this range to the whole byte, limited range values
+    /// outside 16..=235 are clamped.
+    pub fn expand_luma(self, luma: &mut [u8]) {
+        if self == ColorRange::Limited {
+            luma.iter_mut().for_each(|n| *n = LIMITED_LUMA[*n as usize]);
+        }
+    }
+}
+
+/// Matrix the RGB colors are encoded with.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum YuvMatrix {
+    /// Standard definition video and most cameras.
+    #[default]
+    Bt601,
+    /// High definition video.
+    Bt709,
+    /// Ultra high definition video.
+    Bt2020,
+}
+
+/// Fixed point factors of the conversion to RGB, with `PRECISION`
+/// fractional bits.
+struct Coefficients {
+    black: i32,
+    y: i32,
+    r_v: i32,
+    g_u: i32,
+    g_v: i32,
+    b_u: i32,
+}
+
+impl Coefficients {
+    const PRECISION: u32 = 16;
+
+    fn new(matrix: YuvMatrix, range: ColorRange) -> Self {
+        let (kr, kb) = matrix.weights();
+        let kg = 1. - kr - kb;
+        // Stretch the limited range to the whole byte.
+        let (black, luma, chroma) = match range {
+            ColorRange::Limited => (16, 255. / 219., 255. / 224.),
+            ColorRange::Full => (0, 1., 1.),
+        };
+        let fixed = |n: f64| (n * (1 << Self::PRECISION) as f64).round() as i32;
+        Self {
+            black,
+            y: fixed(luma),
+            r_v: fixed(2. * (1. - kr) * chroma),
+            g_u: fixed(2. * kb * (1. - kb) / kg * chroma),
+            g_v: fixed(2. * kr * (1. - kr) / kg * chroma),
+            b_u: fixed(2. * (1. - kb) * chroma),
+        }
+    }
+
+    fn rgb(&self, y: u8, u: u8, v: u8) -> Rgb {
+        let y = (y as i32 - self.black) * self.y + (1 << (Self::PRECISION - 1));
+        let (u, v) = (u as i32 - 128, v as i32 - 128);
+        let clamp = |n: i32| (n >> Self::PRECISION).clamp(0, 255) as u8;
+        Rgb::new(
+            clamp(y + self.r_v * v),
+            clamp(y - self.g_u * u - self.g_v * v),
+            clamp(y + self.b_u * u),
+        )
+    }
+}
+
+impl YuvMatrix {
+    /// Contributions of red and blue to the luma.
+    fn weights(self) -> (f64, f64) {
+        match self {
+            YuvMatrix::Bt601 => (0.299, 0.114),
+            YuvMatrix::Bt709 => (0.2126, 0.0722),
+            YuvMatrix::Bt2020 => (0.2627, 0.0593),
+        }
+    }
+}
+
+impl<'a> Yuv420<'a> {
+    /// Frame without a sample aspect ratio, limited range like decoded video.
+    pub fn new(dimensions: Dimensions, y: &'a [u8], u: &'a [u8], v: &'a [u8]) -> Self {
+        Self { dimensions, y, u, v, sample_aspect_ratio: None, range: ColorRange::Limited }
+    }
+
+    /// Split a buffer holding the three planes one after the other, without
+    /// copying them. `BufferLength` if it's shorter than them, bytes past
+    /// the planes are ignored.
+    pub fn from_i420(dimensions: Dimensions, buffer: &'a [u8]) -> Result<Self> {
+        let luma = dimensions.total();
+        let chroma = chroma_dimensions(&dimensions).total();
+        check_plane(buffer, luma + 2 * chroma)?;
+        let (y, rest) = buffer.split_at(luma);
+        let (u, rest) = rest.split_at(chroma);
+        Ok(Self::new(dimensions, y, u, &rest[..chroma]))
+    }
+
+    /// Dimensions of the U and V planes.
+    pub fn chroma_dimensions(&self) -> Dimensions {
+        chroma_dimensions(&self.dimensions)
+    }
+
+    /// `BufferLength` if a plane is shorter than the dimensions require.
+    pub fn check_len(&self) -> Result<()> {
+        let chroma = self.chroma_dimensions().total();
+        check_plane(self.y, self.dimensions.total())?;
+        check_plane(self.u, chroma)?;
+        check_plane(self.v, chroma)
+    }
+
+    /// The Y plane, `BufferLength` if the buffer is shorter than it.
+    pub fn luma(&self) -> Result<ImageView<'a, u8>> {
+        ImageView::packed(self.y, self.dimensions)
+    }
+
+    /// The U plane, `BufferLength` if the buffer is shorter than it.
+    pub fn u_plane(&self) -> Result<ImageView<'a, u8>> {
+        ImageView::packed(self.u, self.chroma_dimensions())
+    }
+
+    /// The V plane, `BufferLength` if the buffer is shorter than it.
+    pub fn v_plane(&self) -> Result<ImageView<'a, u8>> {
+        ImageView::packed(self.v, self.chroma_dimensions())
+    }
+
+    pub fn to_rgb(&self, matrix: YuvMatrix) -> Result<Buffer2d<Rgb>> {
+        let mut output = Buffer2d::default();
+        self.to_rgb_into(matrix, &mut output)?;
+        Ok(output)
+    }
+
+    /// Like `to_rgb`, reusing the allocation of `output`. Every chroma
+    /// sample colors the 2x2 pixels it covers.
+    pub fn to_rgb_into(&self, matrix: YuvMatrix, output: &mut Buffer2d<Rgb>) -> Result<()> {
+        let (y, u, v) = (self.luma()?, self.u_plane()?, self.v_plane()?);
+        yuv_to_rgb(y, u, v, Coefficients::new(matrix, self.range), output);
+        Ok(())
+    }
+}
+
+impl<'a> YuvSemiPlanar<'a> {
+    /// Frame without a sample aspect ratio. NV21 comes from cameras and
+    /// starts full range, NV12 from video decoders and starts limited range.
+    pub fn new(dimensions: Dimensions, y: &'a [u8], uv: &'a [u8], order: ChromaOrder) -> Self {
+        let range = match order {
+            ChromaOrder::Uv => ColorRange::Limited,
+            ChromaOrder::Vu => ColorRange::Full,
+        };
+        Self { dimensions, y, uv, order, sample_aspect_ratio: None, range }
+    }
+
+    /// Split an NV12 buffer without copying it, `BufferLength` if it's
+    /// shorter than the two planes. Bytes past the planes are ignored.
+    pub fn from_nv12(dimensions: Dimensions, buffer: &'a [u8]) -> Result<Self> {
+        Self::from_buffer(dimensions, buffer, ChromaOrder::Uv)
+    }
+
+    /// Like `from_nv12`, for an NV21 buffer.
+    pub fn from_nv21(dimensions: Dimensions, buffer: &'a [u8]) -> Result<Self> {
+        Self::from_buffer(dimensions, buffer, ChromaOrder::Vu)
+    }
+
+    fn from_buffer(dimensions: Dimensions, buffer: &'a [u8], order: ChromaOrder) -> Result<Self> {
+        let luma = dimensions.total();
+        let chroma = 2 * chroma_dimensions(&dimensions).total();
+        check_plane(buffer, luma + chroma)?;
+        let (y, uv) = buffer.split_at(luma);
+        Ok(Self::new(dimensions, y, &uv[..chroma], order))
+    }
+
+    /// Dimensions of the U and V samples, half the pairs in the chroma
+    /// plane.
+    pub fn chroma_dimensions(&self) -> Dimensions {
+        chroma_dimensions(&self.dimensions)
+    }
+
+    /// `BufferLength` if a plane is shorter than the dimensions require.
+    pub fn check_len(&self) -> Result<()> {
+        check_plane(self.y, self.dimensions.total())?;
+        check_plane(self.uv, 2 * self.chroma_dimensions().total())
+    }
+
+    /// The Y plane, `BufferLength` if the buffer is shorter than it.
+    pub fn luma(&self) -> Result<ImageView<'a, u8>> {
+        ImageView::packed(self.y, self.dimensions)
+    }
+
+    /// The U samples, read in place from the chroma plane.
+    pub fn u_plane(&self) -> Result<ImageView<'a, u8>> {
+        let offset = match self.order {
+            ChromaOrder::Uv => 0,
+            ChromaOrder::Vu => 1,
+        };
+        self.chroma_plane(offset)
+    }
+
+    /// The V samples, read in place from the chroma plane.
+    pub fn v_plane(&self) -> Result<ImageView<'a, u8>> {
+        let offset = match self.order {
+            ChromaOrder::Uv => 1,
+            ChromaOrder::Vu => 0,
+        };
+        self.chroma_plane(offset)
+    }
+
+    fn chroma_plane(&self, offset: usize) -> Result<ImageView<'a, u8>> {
+        let dimensions = self.chroma_dimensions();
+        check_plane(self.uv, 2 * dimensions.total())?;
+        ImageView::with_offset(self.uv, dimensions, offset, 2 * dimensions.width, 2)
+    }
+
+    pub fn to_rgb(&self, matrix: YuvMatrix) -> Result<Buffer2d<Rgb>> {
+        let mut output = Buffer2d::default();
+        self.to_rgb_into(matrix, &mut output)?;
+        Ok(output)
+    }
+
+    /// Like `to_rgb`, reusing the allocation of `output`.
+    pub fn to_rgb_into(&self, matrix: YuvMatrix, output: &mut Buffer2d<Rgb>) -> Result<()> {
+        let (y, u, v) = (self.luma()?, self.u_plane()?, self.v_plane()?);
+        yuv_to_rgb(y, u, v, Coefficients::new(matrix, self.range), output);
+        Ok(())
+    }
+}
+
+impl<'a> Plane<'a> {
+    pub fn new(buffer: &'a [u8], row_stride: usize, pixel_stride: usize) -> Self {
+        Self { buffer, row_stride, pixel_stride }
+    }
+
+    /// `InvalidLayout` if a plane of `dimensions` doesn't fit in the buffer.
+    fn view(&self, dimensions: Dimensions) -> Result<ImageView<'a, u8>> {
+        ImageView::new(self.buffer, dimensions, self.row_stride, self.pixel_stride)
+    }
+}
+
+impl<'a> Yuv420Planes<'a> {
+    /// Frame without a sample aspect ratio, full range like Camera2 frames.
+    pub fn new(dimensions: Dimensions, y: Plane<'a>, u: Plane<'a>, v: Plane<'a>) -> Self {
+        Self { dimensions, y, u, v, sample_aspect_ratio: None, range: ColorRange::Full }
+    }
+
+    /// Dimensions of the U and V planes.
+    pub fn chroma_dimensions(&self) -> Dimensions {
+        chroma_dimensions(&self.dimensions)
+    }
+
+    /// `InvalidLayout` if a plane doesn't fit in its buffer.
+    pub fn check_layout(&self) -> Result<()> {
+        self.luma()?;
+        self.u_plane()?;
+        self.v_plane().map(|_| ())
+    }
+
+    pub fn luma(&self) -> Result<ImageView<'a, u8>> {
+        self.y.view(self.dimensions)
+    }
+
+    pub fn u_plane(&self) -> Result<ImageView<'a, u8>> {
+        self.u.view(self.chroma_dimensions())
+    }
+
+    pub fn v_plane(&self) -> Result<ImageView<'a, u8>> {
+        self.v.view(self.chroma_dimensions())
+    }
+
+    pub fn to_rgb(&self, matrix: YuvMatrix) -> Result<Buffer2d<Rgb>> {
+        let mut output = Buffer2d::default();
+        self.to_rgb_into(matrix, &mut output)?;
+        Ok(output)
+    }
+
+    /// Like `to_rgb`, reusing the allocation of `output`.
+    pub fn to_rgb_into(&self, matrix: YuvMatrix, output: &mut Buffer2d<Rgb>) -> Result<()> {
+        let (y, u, v) = (self.luma()?, self.u_plane()?, self.v_plane()?);
+        yuv_to_rgb(y, u, v, Coefficients::new(matrix, self.range), output);
+        Ok(())
+    }
+}
+
+impl<'a> Yuv422<'a> {
+    /// Frame without a sample aspect ratio, limited range like V4L2 captures.
+    pub fn new(
+        dimensions: Dimensions,
+        buffer: &'a [u8],
+        row_stride: usize,
+        order: Yuv422Order,
+    ) -> Self {
+        Self {
+            dimensions,
+            buffer,
+            row_stride,
+            order,
+            sample_aspect_ratio: None,
+            range: ColorRange::Limited,
+        }
+    }
+
+    /// Frame without padding between the rows.
+    pub fn packed(dimensions: Dimensions, buffer: &'a [u8], order: Yuv422Order) -> Self {
+        Self::new(dimensions, buffer, 4 * dimensions.width.div_ceil(2), order)
+    }
+
+    /// Dimensions of the U and V samples, half the width and the full
+    /// height.
+    pub fn chroma_dimensions(&self) -> Dimensions {
+        Dimensions {
+            height: self.dimensions.height,
+            width: self.dimensions.width.div_ceil(2),
+        }
+    }
+
+    /// `InvalidLayout` if the rows or their pairs don't fit in the buffer.
+    pub fn check_layout(&self) -> Result<()> {
+        self.luma()?;
+        self.u_plane()?;
+        self.v_plane().map(|_| ())
+    }
+
+    /// The luma, read in place every other byte.
+    pub fn luma(&self) -> Result<ImageView<'a, u8>> {
+        let offset = match self.order {
+            Yuv422Order::Yuyv => 0,
+            Yuv422Order::Uyvy => 1,
+        };
+        ImageView::with_offset(self.buffer, self.dimensions, offset, self.row_stride, 2)
+    }
+
+    pub fn u_plane(&self) -> Result<ImageView<'a, u8>> {
+        let offset = match self.order {
+            Yuv422Order::Yuyv => 1,
+            Yuv422Order::Uyvy => 0,
+        };
+        self.chroma_plane(offset)
+    }
+
+    pub fn v_plane(&self) -> Result<ImageView<'a, u8>> {
+        let offset = match self.order {
+            Yuv422Order::Yuyv => 3,
+            Yuv422Order::Uyvy => 2,
+        };
+        self.chroma_plane(offset)
+    }
+
+    fn chroma_plane(&self, offset: usize) -> Result<ImageView<'a, u8>> {
+        ImageView::with_offset(
+            self.buffer,
+            self.chroma_dimensions(),
+            offset,
+            self.row_stride,
+            4,
+        )
+    }
+
+    pub fn to_rgb(&self, matrix: YuvMatrix) -> Result<Buffer2d<Rgb>> {
+        let mut output = Buffer2d::default();
+        self.to_rgb_into(matrix, &mut output)?;
+        Ok(output)
+    }
+
+    /// Like `to_rgb`, reusing the allocation of `output`. Every chroma
+    /// pair colors the two pixels it covers.
+    pub fn to_rgb_into(&self, matrix: YuvMatrix, output: &mut Buffer2d<Rgb>) -> Result<()> {
+        let (y, u, v) = (self.luma()?, self.u_plane()?, self.v_plane()?);
+        yuv_to_rgb(y, u, v, Coefficients::new(matrix, self.range), output);
+        Ok(())
+    }
+}
+
+/// Chroma resolution of a 4:2:0 frame of `dimensions`, odd sizes round up.
+fn chroma_dimensions(dimensions: &Dimensions) -> Dimensions {
+    Dimensions {
+        height: dimensions.height.div_ceil(2),
+        width: dimensions.width.div_ceil(2),
+    }
+}
+
+fn check_plane(plane: &[u8], expected: usize) -> Result<()> {
+    if plane.len() < expected {
+        Err(Error::BufferLength { expected, actual: plane.len() })
+    } else {
+        Ok(())
+    }
+}
+
+/// Convert planes of any layout. `u` and `v` can have a lower resolution
+/// than `y`, every chroma sample then colors the pixels it covers.
+fn yuv_to_rgb(
+    y: ImageView<'_, u8>,
+    u: ImageView<'_, u8>,
+    v: ImageView<'_, u8>,
+    coefficients: Coefficients,
+    output: &mut Buffer2d<Rgb>,
+) {
+    let dimensions = y.dimensions();
+    output.buffer.clear();
+    output.buffer.resize(dimensions.total(), Rgb::default());
+    output.dimensions = dimensions;
+    let chroma = u.dimensions();
+    // Odd sizes are rounded up, so this maps the last pixel to the last
+    // chroma sample.
+    let chroma_x = |x: usize| x * chroma.width / dimensions.width;
+    for_each_row(&mut output.buffer, dimensions.width, |row_index, row| {
+        let chroma_y = row_index * chroma.height / dimensions.height;
+        let (luma, u, v) = (y.row(row_index), u.row(chroma_y), v.row(chroma_y));
+        for (x, rgb) in row.iter_mut().enumerate() {
+            *rgb = coefficients.rgb(luma[x], u[chroma_x(x)], v[chroma_x(x)]);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Dimensions, Rgb, Error};
+    use crate::yuv::{
+        Yuv420, YuvSemiPlanar, Yuv420Planes, Plane, ChromaOrder, Yuv422, Yuv422Order, YuvMatrix,
+        ColorRange,
+    };
+
+    #[test]
+    fn test_from_i420() {
+        // Odd dimensions round the chroma planes up.
+        let dimensions = Dimensions { height: 3, width: 3 };
+        let buffer = (0..9 + 4 + 4 + 2).collect::<Vec<u8>>();
+        let image = Yuv420::from_i420(dimensions, &buffer).unwrap();
+        assert_eq!(image.chroma_dimensions(), Dimensions { height: 2, width: 2 });
+        assert_eq!(image.y, &buffer[..9]);
+        assert_eq!(image.u, &[9, 10, 11, 12]);
+        assert_eq!(image.v, &[13, 14, 15, 16]);
+        // The planes are read in place.
+        assert_eq!(image.y.as_ptr(), buffer.as_ptr());
+        assert_eq!(
+            Yuv420::from_i420(dimensions, &buffer[..16]),
+            Err(Error::BufferLength { expected: 17, actual: 16 }),
+        );
+    }
+
+    #[test]
+    fn test_to_rgb() {
+        let dimensions = Dimensions { height: 2, width: 4 };
+        // Black and white on the left chroma sample, red on the right one.
+        let y = vec![16, 235, 81, 81, 16, 126, 81, 81];
+        let image = Yuv420::new(dimensions, &y, &[128, 90], &[128, 240]);
+        let rgb = image.to_rgb(YuvMatrix::Bt601).unwrap();
+        assert_eq!(rgb.dimensions, dimensions);
+        assert_eq!(rgb.buffer[0], Rgb::new(0, 0, 0));
+        assert_eq!(rgb.buffer[1], Rgb::new(255, 255, 255));
+        assert_eq!(rgb.buffer[5], Rgb::new(128, 128, 128));
+        assert!(rgb.buffer[2..4].iter().chain(&rgb.buffer[6..]).all(|&n| n == rgb.buffer[2]));
+        assert_eq!(rgb.buffer[2], Rgb::new(254, 0, 0));
+    }
+
+    #[test]
+    fn test_matrices() {
+        let dimensions = Dimensions { height: 1, width: 1 };
+        let image = Yuv420::new(dimensions, &[63], &[102], &[240]);
+        // Pure red encoded with BT.709 is off with the other matrices.
+        let red = |matrix| image.to_rgb(matrix).unwrap().buffer[0];
+        assert_eq!(red(YuvMatrix::Bt709), Rgb::new(255, 1, 0));
+        assert_ne!(red(YuvMatrix::Bt601), red(YuvMatrix::Bt709));
+        assert_ne!(red(YuvMatrix::Bt2020), red(YuvMatrix::Bt709));
+    }
+
+    #[test]
+    fn test_short_plane() {
+        let image = Yuv420::new(Dimensions { height: 2, width: 2 }, &[0; 4], &[0], &[]);
+        assert!(image.to_rgb(YuvMatrix::Bt601).is_err());
+    }
+
+    #[test]
+    fn test_semi_planar() {
+        let dimensions = Dimensions { height: 2, width: 4 };
+        let y = vec![16, 235, 81, 81, 16, 126, 81, 81];
+        let planar = Yuv420::new(dimensions, &y, &[128, 90], &[128, 240]);
+        let nv12 = [&y[..], &[128, 128, 90, 240]].concat();
+        let nv21_buffer = [&y[..], &[128, 128, 240, 90]].concat();
+        let nv12 = YuvSemiPlanar::from_nv12(dimensions, &nv12).unwrap();
+        let mut nv21 = YuvSemiPlanar::from_nv21(dimensions, &nv21_buffer).unwrap();
+        assert_eq!(nv21.order, ChromaOrder::Vu);
+        // The planes are read in place.
+        assert_eq!(nv21.uv.as_ptr(), nv21_buffer[8..].as_ptr());
+        assert_eq!((nv12.range, nv21.range), (ColorRange::Limited, ColorRange::Full));
+        nv21.range = ColorRange::Limited;
+        assert_eq!(nv12.u_plane().unwrap().to_buffer_2d().buffer, vec![128, 90]);
+        assert_eq!(nv21.v_plane().unwrap().to_buffer_2d().buffer, vec![128, 240]);
+        let expected = planar.to_rgb(YuvMatrix::Bt709).unwrap();
+        assert_eq!(nv12.to_rgb(YuvMatrix::Bt709).unwrap(), expected);
+        assert_eq!(nv21.to_rgb(YuvMatrix::Bt709).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_semi_planar_len() {
+        let dimensions = Dimensions { height: 3, width: 3 };
+        assert_eq!(
+            YuvSemiPlanar::from_nv21(dimensions, &[0; 16]),
+            Err(Error::BufferLength { expected: 17, actual: 16 }),
+        );
+        let mut image = YuvSemiPlanar::from_nv21(dimensions, &[0; 17]).unwrap();
+        assert!(image.check_len().is_ok());
+        image.uv = &image.uv[..7];
+        assert_eq!(image.check_len(), Err(Error::BufferLength { expected: 8, actual: 7 }));
+        assert!(image.luma().is_ok());
+        assert!(image.u_plane().is_err());
+        image.y = &image.y[..8];
+        assert!(image.luma().is_err());
+    }
+
+    #[test]
+    fn test_planes() {
+        let dimensions = Dimensions { height: 2, width: 4 };
+        let planar = Yuv420::new(
+            dimensions,
+            &[16, 235, 81, 81, 16, 126, 81, 81],
+            &[128, 90],
+            &[128, 240],
+        );
+        // Rows padded to 6 bytes, the last one cut after its last sample.
+        let y = [16, 235, 81, 81, 0, 0, 16, 126, 81, 81];
+        // Interleaved chroma, the planes overlap like in NV21.
+        let vu = [128, 128, 240, 90];
+        let mut image = Yuv420Planes::new(
+            dimensions,
+            Plane::new(&y, 6, 1),
+            Plane::new(&vu[1..], 4, 2),
+            Plane::new(&vu[..3], 4, 2),
+        );
+        assert!(image.check_layout().is_ok());
+        assert_eq!(image.luma().unwrap().to_buffer_2d().buffer, planar.y);
+        assert_eq!(image.u_plane().unwrap().to_buffer_2d().buffer, planar.u);
+        assert_eq!(image.v_plane().unwrap().to_buffer_2d().buffer, planar.v);
+        image.range = ColorRange::Limited;
+        assert_eq!(image.to_rgb(YuvMatrix::Bt601), planar.to_rgb(YuvMatrix::Bt601));
+    }
+
+    #[test]
+    fn test_planes_layout() {
+        let dimensions = Dimensions { height: 2, width: 4 };
+        let chroma = [128; 3];
+        let mut image = Yuv420Planes::new(
+            dimensions,
+            Plane::new(&[0; 8], 4, 1),
+            Plane::new(&chroma, 2, 1),
+            Plane::new(&chroma, 2, 1),
+        );
+        assert!(image.check_layout().is_ok());
+        // A pixel stride of 2 needs one more byte.
+        image.v = Plane::new(&chroma[1..], 4, 2);
+        assert_eq!(image.check_layout(), Err(Error::InvalidLayout));
+        image.v = Plane::new(&chroma, 4, 2);
+        assert!(image.check_layout().is_ok());
+        image.y = Plane::new(&[0; 8], 5, 1);
+        assert_eq!(image.to_rgb(YuvMatrix::Bt601), Err(Error::InvalidLayout));
+    }
+
+    #[test]
+    fn test_yuv422() {
+        let dimensions = Dimensions { height: 2, width: 4 };
+        // A black and white pair, then a red one, on both rows.
+        let yuyv = [16, 128, 235, 128, 81, 90, 81, 240, 16, 128, 235, 128, 81, 90, 81, 240];
+        let uyvy = yuyv.chunks(2).flat_map(|n| [n[1], n[0]]).collect::<Vec<u8>>();
+        let yuyv = Yuv422::packed(dimensions, &yuyv, Yuv422Order::Yuyv);
+        let uyvy = Yuv422::packed(dimensions, &uyvy, Yuv422Order::Uyvy);
+        let luma = vec![16, 235, 81, 81, 16, 235, 81, 81];
+        assert_eq!(yuyv.luma().unwrap().to_buffer_2d().buffer, luma);
+        assert_eq!(uyvy.luma().unwrap().to_buffer_2d().buffer, luma);
+        assert_eq!(yuyv.v_plane().unwrap().to_buffer_2d().buffer, vec![128, 240, 128, 240]);
+
+        let planar = Yuv420::new(dimensions, &luma, &[128, 90], &[128, 240]);
+        let expected = planar.to_rgb(YuvMatrix::Bt601).unwrap();
+        assert_eq!(yuyv.to_rgb(YuvMatrix::Bt601).unwrap(), expected);
+        assert_eq!(uyvy.to_rgb(YuvMatrix::Bt601).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_yuv422_stride() {
+        let dimensions = Dimensions { height: 2, width: 2 };
+        // Rows padded to 6 bytes, the last one cut after its pair.
+        let buffer = [10, 128, 20, 128, 0, 0, 30, 128, 40, 128];
+        let mut image = Yuv422::new(dimensions, &buffer, 6, Yuv422Order::Yuyv);
+        assert!(image.check_layout().is_ok());
+        assert_eq!(image.luma().unwrap().to_buffer_2d().buffer, vec![10, 20, 30, 40]);
+        image.row_stride = 7;
+        assert_eq!(image.check_layout(), Err(Error::InvalidLayout));
+        // The last pair of an odd width must be whole.
+        let image = Yuv422::packed(Dimensions { height: 1, width: 3 }, &buffer[..7], image.order);
+        assert_eq!(image.to_rgb(YuvMatrix::Bt601), Err(Error::InvalidLayout));
+    }
+
+    #[test]
+    fn test_expand_luma() {
+        let mut luma = [0, 16, 17, 126, 235, 240, 255];
+        ColorRange::Full.expand_luma(&mut luma);
+        assert_eq!(luma, [0, 16, 17, 126, 235, 240, 255]);
+        ColorRange::Limited.expand_luma(&mut luma);
+        assert_eq!(luma, [0, 0, 1, 128, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_full_range_rgb() {
+        let dimensions = Dimensions { height: 1, width: 2 };
+        let mut image = Yuv420::new(dimensions, &[0, 255], &[128], &[128]);
+        image.range = ColorRange::Full;
+        let rgb = image.to_rgb(YuvMatrix::Bt601).unwrap();
+        assert_eq!(rgb.buffer, vec![Rgb::new(0, 0, 0), Rgb::new(255, 255, 255)]);
+        // Full range red, from the JPEG equations.
+        let image = Yuv420Planes::new(
+            Dimensions { height: 1, width: 1 },
+            Plane::new(&[76], 1, 1),
+            Plane::new(&[85], 1, 1),
+            Plane::new(&[255], 1, 1),
+        );
+        assert_eq!(image.to_rgb(YuvMatrix::Bt601).unwrap().buffer, vec![Rgb::new(254, 0, 0)]);
+    }
 }